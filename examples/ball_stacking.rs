@@ -22,7 +22,7 @@ fn main() {
                 ..default()
             })
             ,
-            XPBDPlugin,
+            XPBDPlugin::default(),
             LogDiagnosticsPlugin::default(),
             FrameTimeDiagnosticsPlugin,
         ))
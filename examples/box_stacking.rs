@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use bevy_xpbd::{
+    components::{BoxCollider, CircleCollider, Pos},
+    entity::{DynamicBoxBundle, ParticleBundle, StaticBoxBundle},
+    XPBDPlugin,
+};
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::rgb(0.8, 0.8, 0.9)))
+        .insert_resource(Msaa::Sample4)
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: (480., 360.).into(),
+                    ..default()
+                }),
+                ..default()
+            }),
+            XPBDPlugin::default(),
+        ))
+        .add_systems(Startup, (spawn_camera, spawn_crates))
+        .run()
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0., 0., 10.).looking_at(Vec3::new(0., 0., 0.), Vec3::Y),
+        ..default()
+    });
+}
+
+fn spawn_crates(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let quad = meshes.add(Rectangle::new(1., 1.).mesh());
+    let sphere = meshes.add(Sphere::new(1.).mesh().ico(4).unwrap());
+    let blue = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.4, 0.6),
+        unlit: true,
+        ..default()
+    });
+    let brown = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.6, 0.45, 0.3),
+        unlit: true,
+        ..default()
+    });
+
+    let floor_size = Vec2::new(20., 2.);
+    commands.spawn((
+        PbrBundle {
+            mesh: quad.clone(),
+            material: blue.clone(),
+            transform: Transform::from_scale(floor_size.extend(1.)),
+            ..default()
+        },
+        StaticBoxBundle {
+            pos: Pos(Vec2::new(0., -4.)),
+            collider: BoxCollider { size: floor_size },
+            ..default()
+        },
+    ));
+
+    let size = Vec2::splat(0.5);
+    for i in 0..6 {
+        for j in 0..3 {
+            let pos = Vec2::new((j as f32 - 1.) * 1.5, i as f32 * size.y - 2.75);
+            commands.spawn((
+                PbrBundle {
+                    mesh: quad.clone(),
+                    material: brown.clone(),
+                    transform: Transform {
+                        scale: size.extend(1.),
+                        translation: pos.extend(0.),
+                        ..default()
+                    },
+                    ..default()
+                },
+                DynamicBoxBundle {
+                    collider: BoxCollider { size },
+                    ..DynamicBoxBundle::new_with_pos_and_vel(pos, Vec2::ZERO)
+                },
+            ));
+        }
+    }
+
+    let radius = 0.2;
+    let pos = Vec2::new(-1.5, 2.);
+    commands.spawn((
+        PbrBundle {
+            mesh: sphere,
+            material: blue,
+            transform: Transform {
+                scale: Vec3::splat(radius),
+                translation: pos.extend(0.),
+                ..default()
+            },
+            ..default()
+        },
+        ParticleBundle {
+            collider: CircleCollider { radius },
+            ..ParticleBundle::new_with_pos_and_vel(pos, Vec2::ZERO)
+        },
+    ));
+}
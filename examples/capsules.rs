@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+use bevy_xpbd::{
+    components::{BoxCollider, CapsuleCollider, Pos, Rot},
+    entity::{DynamicBoxBundle, DynamicCapsuleBundle, StaticBoxBundle, StaticCapsuleBundle},
+    XPBDPlugin,
+};
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::rgb(0.8, 0.8, 0.9)))
+        .insert_resource(Msaa::Sample4)
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: (480., 360.).into(),
+                    ..default()
+                }),
+                ..default()
+            }),
+            XPBDPlugin::default(),
+        ))
+        .add_systems(Startup, (spawn_camera, spawn_capsules))
+        .run()
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0., 0., 10.).looking_at(Vec3::new(0., 0., 0.), Vec3::Y),
+        ..default()
+    });
+}
+
+fn spawn_capsules(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let quad = meshes.add(Rectangle::new(1., 1.).mesh());
+    let blue = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.4, 0.6),
+        unlit: true,
+        ..default()
+    });
+    let brown = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.6, 0.45, 0.3),
+        unlit: true,
+        ..default()
+    });
+    let green = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.6, 0.4),
+        unlit: true,
+        ..default()
+    });
+
+    let floor_size = Vec2::new(20., 2.);
+    commands.spawn((
+        PbrBundle {
+            mesh: quad.clone(),
+            material: blue.clone(),
+            transform: Transform::from_scale(floor_size.extend(1.)),
+            ..default()
+        },
+        StaticBoxBundle {
+            pos: Pos(Vec2::new(0., -4.)),
+            collider: BoxCollider { size: floor_size },
+            ..default()
+        },
+    ));
+
+    // A long tilted capsule as a ramp for the others to roll down
+    let ramp = CapsuleCollider {
+        half_height: 2.,
+        radius: 0.2,
+    };
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Capsule2d::new(ramp.radius, 2. * ramp.half_height).mesh()),
+            material: blue,
+            ..default()
+        },
+        StaticCapsuleBundle {
+            pos: Pos(Vec2::new(-1.5, -1.5)),
+            rot: Rot(1.2),
+            collider: ramp,
+            ..default()
+        },
+    ));
+
+    let capsule = CapsuleCollider {
+        half_height: 0.3,
+        radius: 0.2,
+    };
+    let capsule_mesh = meshes.add(Capsule2d::new(capsule.radius, 2. * capsule.half_height).mesh());
+    for i in 0..12 {
+        let pos = Vec2::new((i % 4) as f32 * 0.9 - 2.5, (i / 4) as f32 * 1.2 + 0.5);
+        commands.spawn((
+            PbrBundle {
+                mesh: capsule_mesh.clone(),
+                material: green.clone(),
+                ..default()
+            },
+            DynamicCapsuleBundle {
+                rot: Rot(i as f32 * 0.7),
+                collider: CapsuleCollider {
+                    half_height: capsule.half_height,
+                    radius: capsule.radius,
+                },
+                ..DynamicCapsuleBundle::new_with_pos_and_vel(pos, Vec2::ZERO)
+            },
+        ));
+    }
+
+    let size = Vec2::splat(0.6);
+    for i in 0..3 {
+        let pos = Vec2::new(2.5, i as f32 * size.y - 2.7);
+        commands.spawn((
+            PbrBundle {
+                mesh: quad.clone(),
+                material: brown.clone(),
+                transform: Transform {
+                    scale: size.extend(1.),
+                    translation: pos.extend(0.),
+                    ..default()
+                },
+                ..default()
+            },
+            DynamicBoxBundle {
+                collider: BoxCollider { size },
+                ..DynamicBoxBundle::new_with_pos_and_vel(pos, Vec2::ZERO)
+            },
+        ));
+    }
+}
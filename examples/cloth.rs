@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use bevy_xpbd::{
+    components::CircleCollider,
+    entity::{spawn_cloth, ClothSettings, ParticleBundle},
+    joints::DistanceJoint,
+    XPBDPlugin,
+};
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::rgb(0.8, 0.8, 0.9)))
+        .insert_resource(Msaa::Sample4)
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: (480., 360.).into(),
+                    ..default()
+                }),
+                ..default()
+            }),
+            XPBDPlugin::default(),
+        ))
+        .add_systems(Startup, (spawn_camera, spawn_curtain))
+        .run()
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0., 0., 10.).looking_at(Vec3::new(0., 0., 0.), Vec3::Y),
+        ..default()
+    });
+}
+
+fn spawn_curtain(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let sphere = meshes.add(Sphere::new(1.).mesh().ico(2).unwrap());
+    let blue = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.4, 0.6),
+        unlit: true,
+        ..default()
+    });
+    let red = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.7, 0.3, 0.3),
+        unlit: true,
+        ..default()
+    });
+
+    // A curtain hanging from its top row, the heavy ball tied to its bottom tears it apart
+    let settings = ClothSettings {
+        origin: Vec2::new(-2., 2.5),
+        columns: 20,
+        rows: 16,
+        max_stretch: Some(1.2),
+        ..default()
+    };
+    let cloth = spawn_cloth(&mut commands, &settings);
+    for column in (0..settings.columns).step_by(3) {
+        cloth.pin(&mut commands, column, 0);
+    }
+    for particle in cloth.particles.iter() {
+        commands.entity(*particle).insert(PbrBundle {
+            mesh: sphere.clone(),
+            material: red.clone(),
+            transform: Transform::from_scale(Vec3::splat(settings.spacing / 2.)),
+            ..default()
+        });
+    }
+
+    let radius = 0.4;
+    let pos = Vec2::new(0., -1.5);
+    let ball = commands
+        .spawn((
+            PbrBundle {
+                mesh: sphere,
+                material: blue,
+                transform: Transform {
+                    scale: Vec3::splat(radius),
+                    translation: pos.extend(0.),
+                    ..default()
+                },
+                ..default()
+            },
+            ParticleBundle {
+                collider: CircleCollider { radius },
+                ..ParticleBundle::new_with_pos_and_vel_and_mass(pos, Vec2::ZERO, 200.)
+            },
+        ))
+        .id();
+    let bottom = cloth.particle(settings.columns / 2, settings.rows - 1);
+    commands.spawn(DistanceJoint::new(bottom, ball, 0.7));
+}
@@ -0,0 +1,121 @@
+use bevy::prelude::*;
+use bevy_xpbd::{
+    components::{BoxCollider, CircleCollider, Mass, Pos},
+    entity::{spawn_fluid_block, DynamicBoxBundle, ParticleBundle, StaticBoxBundle},
+    fluid::FluidSettings,
+    XPBDPlugin,
+};
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::rgb(0.8, 0.8, 0.9)))
+        .insert_resource(Msaa::Sample4)
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: (480., 360.).into(),
+                    ..default()
+                }),
+                ..default()
+            }),
+            XPBDPlugin::default(),
+        ))
+        .add_systems(Startup, (spawn_camera, spawn_tank))
+        .run()
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0., 0., 10.).looking_at(Vec3::new(0., 0., 0.), Vec3::Y),
+        ..default()
+    });
+}
+
+fn spawn_tank(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    settings: Res<FluidSettings>,
+) {
+    let quad = meshes.add(Rectangle::new(1., 1.).mesh());
+    let sphere = meshes.add(Sphere::new(1.).mesh().ico(2).unwrap());
+    let blue = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.2, 0.4, 0.8),
+        unlit: true,
+        ..default()
+    });
+    let brown = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.6, 0.45, 0.3),
+        unlit: true,
+        ..default()
+    });
+    let grey = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.4, 0.5),
+        unlit: true,
+        ..default()
+    });
+
+    // Floor and the two sides of the tank
+    for (pos, size) in [
+        (Vec2::new(0., -3.5), Vec2::new(10., 1.)),
+        (Vec2::new(-4.5, 0.), Vec2::new(1., 8.)),
+        (Vec2::new(4.5, 0.), Vec2::new(1., 8.)),
+    ] {
+        commands.spawn((
+            PbrBundle {
+                mesh: quad.clone(),
+                material: grey.clone(),
+                transform: Transform::from_scale(size.extend(1.)),
+                ..default()
+            },
+            StaticBoxBundle {
+                pos: Pos(pos),
+                collider: BoxCollider { size },
+                ..default()
+            },
+        ));
+    }
+
+    // A column of water against the left side that collapses across the tank
+    let particles = spawn_fluid_block(&mut commands, &settings, Vec2::new(-3.9, -2.9), 12, 25);
+    let radius = settings.kernel_radius / 4.;
+    for particle in particles {
+        commands.entity(particle).insert(PbrBundle {
+            mesh: sphere.clone(),
+            material: blue.clone(),
+            transform: Transform::from_scale(Vec3::splat(radius)),
+            ..default()
+        });
+    }
+
+    // A wooden crate that floats on the wave and an iron ball that sinks through it
+    let size = Vec2::splat(0.6);
+    let pos = Vec2::new(2., 1.);
+    commands.spawn((
+        PbrBundle {
+            mesh: quad,
+            material: brown,
+            transform: Transform::from_scale(size.extend(1.)),
+            ..default()
+        },
+        DynamicBoxBundle {
+            mass: Mass(3.),
+            collider: BoxCollider { size },
+            ..DynamicBoxBundle::new_with_pos_and_vel(pos, Vec2::ZERO)
+        },
+    ));
+    let radius = 0.3;
+    let pos = Vec2::new(0.5, 2.);
+    commands.spawn((
+        PbrBundle {
+            mesh: sphere,
+            material: grey,
+            transform: Transform::from_scale(Vec3::splat(radius)),
+            ..default()
+        },
+        ParticleBundle {
+            collider: CircleCollider { radius },
+            ..ParticleBundle::new_with_pos_and_vel_and_mass(pos, Vec2::ZERO, 30.)
+        },
+    ));
+}
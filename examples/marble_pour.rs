@@ -2,7 +2,7 @@ use bevy::prelude::*;
 use bevy_xpbd::{
     components::{BoxCollider, CircleCollider, Pos},
     entity::{ParticleBundle, StaticBoxBundle},
-    XPBDPlugin, DELTA_TIME,
+    XPBDPlugin,
 };
 use rand::random;
 
@@ -10,7 +10,6 @@ fn main() {
     App::new()
         .insert_resource(ClearColor(Color::rgb(0.8, 0.8, 0.9)))
         .insert_resource(Msaa::Sample4)
-        .insert_resource(Time::<Fixed>::from_seconds(DELTA_TIME.into()))
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 resolution: (480., 360.).into(),
@@ -19,7 +18,7 @@ fn main() {
             }),
             ..default()
         }))
-        .add_plugins(XPBDPlugin)
+        .add_plugins(XPBDPlugin::default())
         .add_systems(Startup, startup)
         .add_systems(FixedUpdate, (spawn_marble, despawn_marble))
         .run()
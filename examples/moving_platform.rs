@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use bevy_xpbd::{
+    components::{BoxCollider, Pos, Velocity},
+    entity::{DynamicBoxBundle, KinematicBoxBundle, StaticBoxBundle},
+    XPBDPlugin,
+};
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::rgb(0.8, 0.8, 0.9)))
+        .insert_resource(Msaa::Sample4)
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: (480., 360.).into(),
+                    ..default()
+                }),
+                ..default()
+            }),
+            XPBDPlugin::default(),
+        ))
+        .add_systems(Startup, (spawn_camera, spawn_platforms))
+        .add_systems(Update, move_platforms)
+        .run()
+}
+
+/// Swings the platform from side to side around where it started.
+#[derive(Component)]
+struct Shuttle {
+    origin: Vec2,
+    extent: Vec2,
+    speed: f32,
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0., 0., 10.).looking_at(Vec3::new(0., 0., 0.), Vec3::Y),
+        ..default()
+    });
+}
+
+fn spawn_platforms(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let quad = meshes.add(Rectangle::new(1., 1.).mesh());
+    let blue = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.4, 0.6),
+        unlit: true,
+        ..default()
+    });
+    let brown = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.6, 0.45, 0.3),
+        unlit: true,
+        ..default()
+    });
+
+    let floor_size = Vec2::new(20., 1.);
+    commands.spawn((
+        PbrBundle {
+            mesh: quad.clone(),
+            material: blue.clone(),
+            transform: Transform::from_scale(floor_size.extend(1.)),
+            ..default()
+        },
+        StaticBoxBundle {
+            pos: Pos(Vec2::new(0., -3.5)),
+            collider: BoxCollider { size: floor_size },
+            ..default()
+        },
+    ));
+
+    // A platform going back and forth and a lift going up and down, each with a crate on top
+    for (origin, extent) in [
+        (Vec2::new(-1., -1.), Vec2::new(2., 0.)),
+        (Vec2::new(3.5, -1.5), Vec2::new(0., 1.5)),
+    ] {
+        let size = Vec2::new(2., 0.3);
+        commands.spawn((
+            PbrBundle {
+                mesh: quad.clone(),
+                material: blue.clone(),
+                transform: Transform::from_scale(size.extend(1.)),
+                ..default()
+            },
+            KinematicBoxBundle {
+                collider: BoxCollider { size },
+                ..KinematicBoxBundle::new_with_pos_and_vel(origin, Vec2::ZERO)
+            },
+            Shuttle {
+                origin,
+                extent,
+                speed: 1.,
+            },
+        ));
+
+        let size = Vec2::splat(0.5);
+        commands.spawn((
+            PbrBundle {
+                mesh: quad.clone(),
+                material: brown.clone(),
+                transform: Transform::from_scale(size.extend(1.)),
+                ..default()
+            },
+            DynamicBoxBundle {
+                collider: BoxCollider { size },
+                ..DynamicBoxBundle::new_with_pos_and_vel(origin + Vec2::Y * 0.4, Vec2::ZERO)
+            },
+        ));
+    }
+}
+
+fn move_platforms(
+    mut platforms: Query<(&Shuttle, &Pos, &mut Velocity)>,
+    time: Res<Time>,
+) {
+    for (shuttle, pos, mut vel) in platforms.iter_mut() {
+        // Steer towards where the platform should be a moment from now
+        let t = time.elapsed_seconds() + 0.1;
+        let target = shuttle.origin + shuttle.extent * (t * shuttle.speed).sin();
+        vel.0 = (target - pos.0) / 0.1;
+    }
+}
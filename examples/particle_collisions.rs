@@ -16,7 +16,7 @@ fn main() {
         .insert_resource(Msaa::Sample4)
         .insert_resource(Gravity(Vec2::ZERO))
         .add_plugins(DefaultPlugins)
-        .add_plugins(XPBDPlugin)
+        .add_plugins(XPBDPlugin::default())
         .add_systems(Startup, (spawn_sphere, spawn_camera))
         .run();
 }
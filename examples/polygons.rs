@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use bevy_xpbd::{
+    components::{BoxCollider, ConvexPolygonCollider, Pos, Rot},
+    entity::{DynamicConvexPolygonBundle, StaticBoxBundle, StaticConvexPolygonBundle},
+    XPBDPlugin,
+};
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::rgb(0.8, 0.8, 0.9)))
+        .insert_resource(Msaa::Sample4)
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: (480., 360.).into(),
+                    ..default()
+                }),
+                ..default()
+            }),
+            XPBDPlugin::default(),
+        ))
+        .add_systems(Startup, (spawn_camera, spawn_polygons))
+        .run()
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0., 0., 10.).looking_at(Vec3::new(0., 0., 0.), Vec3::Y),
+        ..default()
+    });
+}
+
+fn spawn_polygons(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let quad = meshes.add(Rectangle::new(1., 1.).mesh());
+    let blue = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.4, 0.6),
+        unlit: true,
+        ..default()
+    });
+    let brown = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.6, 0.45, 0.3),
+        unlit: true,
+        ..default()
+    });
+    let green = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.6, 0.4),
+        unlit: true,
+        ..default()
+    });
+
+    let floor_size = Vec2::new(20., 2.);
+    commands.spawn((
+        PbrBundle {
+            mesh: quad.clone(),
+            material: blue.clone(),
+            transform: Transform::from_scale(floor_size.extend(1.)),
+            ..default()
+        },
+        StaticBoxBundle {
+            pos: Pos(Vec2::new(0., -4.)),
+            collider: BoxCollider { size: floor_size },
+            ..default()
+        },
+    ));
+
+    // A static wedge for the others to slide down
+    let wedge = ConvexPolygonCollider::new(vec![
+        Vec2::new(-2., -0.5),
+        Vec2::new(2., -0.5),
+        Vec2::new(-2., 1.),
+    ]);
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(
+                Triangle2d::new(wedge.vertices[0], wedge.vertices[1], wedge.vertices[2]).mesh(),
+            ),
+            material: blue,
+            ..default()
+        },
+        StaticConvexPolygonBundle {
+            pos: Pos(Vec2::new(-2.5, -2.5)),
+            collider: wedge,
+            ..default()
+        },
+    ));
+
+    let radius = 0.35;
+    for i in 0..12 {
+        let sides = 3 + i % 4;
+        let pos = Vec2::new((i % 4) as f32 * 0.9 - 3., (i / 4) as f32 * 1.2 + 0.5);
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(RegularPolygon::new(radius, sides).mesh()),
+                material: if i % 2 == 0 {
+                    green.clone()
+                } else {
+                    brown.clone()
+                },
+                ..default()
+            },
+            DynamicConvexPolygonBundle {
+                rot: Rot(i as f32 * 0.7),
+                collider: ConvexPolygonCollider::regular(sides, radius),
+                ..DynamicConvexPolygonBundle::new_with_pos_and_vel(pos, Vec2::ZERO)
+            },
+        ));
+    }
+}
@@ -0,0 +1,101 @@
+use bevy::prelude::*;
+use bevy_xpbd::{
+    components::CircleCollider,
+    entity::{spawn_rope, ParticleBundle},
+    XPBDPlugin,
+};
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::rgb(0.8, 0.8, 0.9)))
+        .insert_resource(Msaa::Sample4)
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: (480., 360.).into(),
+                    ..default()
+                }),
+                ..default()
+            }),
+            XPBDPlugin::default(),
+        ))
+        .add_systems(Startup, (spawn_camera, spawn_ropes))
+        .run()
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0., 0., 10.).looking_at(Vec3::new(0., 0., 0.), Vec3::Y),
+        ..default()
+    });
+}
+
+fn spawn_ropes(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let sphere = meshes.add(Sphere::new(1.).mesh().ico(4).unwrap());
+    let blue = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.4, 0.6),
+        unlit: true,
+        ..default()
+    });
+    let brown = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.6, 0.45, 0.3),
+        unlit: true,
+        ..default()
+    });
+
+    // A bridge held at both ends and a chain hanging from one end
+    let bridge = spawn_rope(
+        &mut commands,
+        Vec2::new(-3., 0.),
+        Vec2::new(3., 0.),
+        40,
+        0.05,
+        0.,
+    );
+    bridge.pin_start(&mut commands);
+    bridge.pin_end(&mut commands);
+    let chain = spawn_rope(
+        &mut commands,
+        Vec2::new(-4., 2.),
+        Vec2::new(-1., 2.),
+        12,
+        0.1,
+        0.,
+    );
+    chain.pin_start(&mut commands);
+
+    for rope in [&bridge, &chain] {
+        let radius = (rope.end - rope.start).length() / (rope.links.len() - 1) as f32 / 2.;
+        for link in rope.links.iter() {
+            commands.entity(*link).insert(PbrBundle {
+                mesh: sphere.clone(),
+                material: brown.clone(),
+                transform: Transform::from_scale(Vec3::splat(radius)),
+                ..default()
+            });
+        }
+    }
+
+    let radius = 0.4;
+    let pos = Vec2::new(0.5, 3.);
+    commands.spawn((
+        PbrBundle {
+            mesh: sphere,
+            material: blue,
+            transform: Transform {
+                scale: Vec3::splat(radius),
+                translation: pos.extend(0.),
+                ..default()
+            },
+            ..default()
+        },
+        ParticleBundle {
+            collider: CircleCollider { radius },
+            ..ParticleBundle::new_with_pos_and_vel(pos, Vec2::ZERO)
+        },
+    ));
+}
@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use bevy_xpbd::{
+    components::{CircleCollider, ConvexPolygonCollider, PolylineCollider},
+    entity::{DynamicConvexPolygonBundle, ParticleBundle, StaticPolylineBundle},
+    XPBDPlugin,
+};
+
+fn main() {
+    App::new()
+        .insert_resource(ClearColor(Color::rgb(0.8, 0.8, 0.9)))
+        .insert_resource(Msaa::Sample4)
+        .add_plugins((
+            DefaultPlugins.set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: (480., 360.).into(),
+                    ..default()
+                }),
+                ..default()
+            }),
+            XPBDPlugin::default(),
+        ))
+        .add_systems(Startup, (spawn_camera, spawn_terrain))
+        .run()
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera3dBundle {
+        transform: Transform::from_xyz(0., 0., 10.).looking_at(Vec3::new(0., 0., 0.), Vec3::Y),
+        ..default()
+    });
+}
+
+fn spawn_terrain(
+    mut commands: Commands,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    let quad = meshes.add(Rectangle::new(1., 1.).mesh());
+    let sphere = meshes.add(Sphere::new(1.).mesh().ico(4).unwrap());
+    let blue = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.4, 0.6),
+        unlit: true,
+        ..default()
+    });
+    let brown = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.6, 0.45, 0.3),
+        unlit: true,
+        ..default()
+    });
+    let green = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.6, 0.4),
+        unlit: true,
+        ..default()
+    });
+
+    // A slope down into a flat stretch split into several segments, a small valley and a bump.
+    // Listed from left to right and reversed so the ground faces up
+    let mut ground = vec![
+        Vec2::new(-5.5, 3.),
+        Vec2::new(-4.5, 1.),
+        Vec2::new(-3., -0.5),
+        Vec2::new(-1.5, -2.),
+        Vec2::new(-0.5, -2.5),
+        Vec2::new(0.5, -2.5),
+        Vec2::new(1.5, -2.5),
+        Vec2::new(2.3, -2.9),
+        Vec2::new(3., -2.2),
+        Vec2::new(3.8, -2.5),
+        Vec2::new(5.5, 1.),
+    ];
+    ground.reverse();
+    commands
+        .spawn((
+            SpatialBundle::default(),
+            StaticPolylineBundle {
+                collider: PolylineCollider::new(ground.clone()),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            for segment in ground.windows(2) {
+                let (start, end) = (segment[0], segment[1]);
+                let edge = end - start;
+                parent.spawn(PbrBundle {
+                    mesh: quad.clone(),
+                    material: blue.clone(),
+                    transform: Transform {
+                        translation: ((start + end) / 2.).extend(0.),
+                        rotation: Quat::from_rotation_z(edge.to_angle()),
+                        scale: Vec3::new(edge.length(), 0.05, 1.),
+                    },
+                    ..default()
+                });
+            }
+        });
+
+    for i in 0..6 {
+        let pos = Vec2::new(-5. + (i % 2) as f32 * 0.5, 3.5 + i as f32 * 0.6);
+        let radius = 0.2;
+        commands.spawn((
+            PbrBundle {
+                mesh: sphere.clone(),
+                material: green.clone(),
+                transform: Transform {
+                    scale: Vec3::splat(radius),
+                    translation: pos.extend(0.),
+                    ..default()
+                },
+                ..default()
+            },
+            ParticleBundle {
+                collider: CircleCollider { radius },
+                ..ParticleBundle::new_with_pos_and_vel(pos, Vec2::ZERO)
+            },
+        ));
+    }
+
+    for i in 0..4 {
+        let sides = 4 + i % 3;
+        let radius = 0.3;
+        let pos = Vec2::new(-3.8 + i as f32 * 0.7, 1.5 + i as f32 * 0.5);
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(RegularPolygon::new(radius, sides).mesh()),
+                material: brown.clone(),
+                ..default()
+            },
+            DynamicConvexPolygonBundle {
+                collider: ConvexPolygonCollider::regular(sides, radius),
+                ..DynamicConvexPolygonBundle::new_with_pos_and_vel(pos, Vec2::ZERO)
+            },
+        ));
+    }
+}
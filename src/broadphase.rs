@@ -0,0 +1,679 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::components::*;
+use crate::narrowphase::ColliderQuery;
+use crate::resources::{CollisionPairs, DeltaTime};
+
+/// Axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_center_half_extents(center: Vec2, half_extents: Vec2) -> Self {
+        Self::new(center - half_extents, center + half_extents)
+    }
+
+    pub fn grow(&self, margin: f32) -> Self {
+        Self::new(self.min - margin, self.max + margin)
+    }
+
+    pub fn merge(&self, other: &Aabb) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn perimeter(&self) -> f32 {
+        let size = self.max - self.min;
+        2. * (size.x + size.y)
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.min).all() && self.max.cmpge(other.max).all()
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        self.min.cmple(point).all() && self.max.cmpge(point).all()
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+}
+
+/// Finds the pairs of bodies that may be touching so the narrowphase doesn't have to test
+/// every body against every other one.
+///
+/// Pick an implementation with [`XPBDPlugin::with_broadphase`](crate::XPBDPlugin::with_broadphase),
+/// and insert the resource yourself before adding the plugin to configure it.
+pub trait Broadphase: Resource {
+    /// Rebuilds the structure from scratch.
+    fn build(&mut self, proxies: &[(Entity, Aabb)]);
+
+    /// Brings the structure up to date with this step's bounding boxes.
+    fn update(&mut self, proxies: &[(Entity, Aabb)]) {
+        self.build(proxies);
+    }
+
+    /// Pushes every pair of bodies whose bounding boxes overlap.
+    fn query_pairs(&self, pairs: &mut Vec<(Entity, Entity)>);
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn collect_collision_pairs<B: Broadphase>(
+    colliders: Query<
+        (Entity, &Pos, &Rot, Option<&Velocity>, ColliderQuery),
+        Or<(
+            With<CircleCollider>,
+            With<BoxCollider>,
+            With<CapsuleCollider>,
+            With<ConvexPolygonCollider>,
+            With<PolylineCollider>,
+        )>,
+    >,
+    filters: Query<(
+        Option<&RigidBody>,
+        Has<FluidParticle>,
+        Option<&CollisionGroup>,
+    )>,
+    dt: Res<DeltaTime>,
+    mut broadphase: ResMut<B>,
+    mut collision_pairs: ResMut<CollisionPairs>,
+    mut proxies: Local<Vec<(Entity, Aabb)>>,
+    mut built: Local<bool>,
+) {
+    proxies.clear();
+    let k = 2.;
+    let safety_margin_factor = k * dt.0;
+    for (entity, pos, rot, vel, collider) in colliders.iter() {
+        let Some(shape) = collider.shape() else {
+            continue;
+        };
+        let safety_margin = vel.map_or(0., |vel| safety_margin_factor * vel.0.length());
+        proxies.push((entity, shape.aabb(pos.0, rot.0).grow(safety_margin)));
+    }
+
+    if *built {
+        broadphase.update(&proxies);
+    } else {
+        broadphase.build(&proxies);
+        *built = true;
+    }
+
+    collision_pairs.0.clear();
+    broadphase.query_pairs(&mut collision_pairs.0);
+    // Static and kinematic bodies don't collide with each other, and neither do bodies of the
+    // same group. Fluid particles are kept apart by the fluid solver instead. Pairs with a static
+    // or kinematic body list the dynamic one first.
+    collision_pairs.0.retain_mut(|(entity_a, entity_b)| {
+        let (Ok((rigid_body_a, fluid_a, group_a)), Ok((rigid_body_b, fluid_b, group_b))) =
+            (filters.get(*entity_a), filters.get(*entity_b))
+        else {
+            return false;
+        };
+        let dynamic_a = rigid_body_a.is_some_and(|rigid_body| rigid_body.is_dynamic());
+        let dynamic_b = rigid_body_b.is_some_and(|rigid_body| rigid_body.is_dynamic());
+        if !dynamic_a {
+            std::mem::swap(entity_a, entity_b);
+        }
+        (dynamic_a || dynamic_b)
+            && !(fluid_a && fluid_b)
+            && (group_a.is_none() || group_a != group_b)
+    });
+}
+
+/// Tests every body against every other one.
+#[derive(Debug, Default, Resource)]
+pub struct BruteForce {
+    proxies: Vec<(Entity, Aabb)>,
+}
+
+impl Broadphase for BruteForce {
+    fn build(&mut self, proxies: &[(Entity, Aabb)]) {
+        self.proxies.clear();
+        self.proxies.extend_from_slice(proxies);
+    }
+
+    fn query_pairs(&self, pairs: &mut Vec<(Entity, Entity)>) {
+        for (i, (entity_a, aabb_a)) in self.proxies.iter().enumerate() {
+            for (entity_b, aabb_b) in self.proxies[i + 1..].iter() {
+                if aabb_a.intersects(aabb_b) {
+                    pairs.push((*entity_a, *entity_b));
+                }
+            }
+        }
+    }
+}
+
+/// Keeps bodies sorted along the x axis and only tests the ones whose x intervals overlap.
+#[derive(Debug, Default, Resource)]
+pub struct SweepAndPrune {
+    proxies: Vec<(Entity, Aabb)>,
+}
+
+impl SweepAndPrune {
+    fn sort(&mut self) {
+        // Bodies barely move between steps, so the list is almost sorted already and insertion
+        // sort runs in close to linear time.
+        for i in 1..self.proxies.len() {
+            let mut j = i;
+            while j > 0 && self.proxies[j - 1].1.min.x > self.proxies[j].1.min.x {
+                self.proxies.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+    }
+}
+
+impl Broadphase for SweepAndPrune {
+    fn build(&mut self, proxies: &[(Entity, Aabb)]) {
+        self.proxies.clear();
+        self.proxies.extend_from_slice(proxies);
+        self.proxies
+            .sort_unstable_by(|(_, a), (_, b)| a.min.x.total_cmp(&b.min.x));
+    }
+
+    fn update(&mut self, proxies: &[(Entity, Aabb)]) {
+        let mut current: HashMap<Entity, Aabb> = proxies.iter().copied().collect();
+        self.proxies
+            .retain_mut(|(entity, aabb)| match current.remove(entity) {
+                Some(new_aabb) => {
+                    *aabb = new_aabb;
+                    true
+                }
+                None => false,
+            });
+        self.proxies.extend(current);
+        self.sort();
+    }
+
+    fn query_pairs(&self, pairs: &mut Vec<(Entity, Entity)>) {
+        for (i, (entity_a, aabb_a)) in self.proxies.iter().enumerate() {
+            for (entity_b, aabb_b) in self.proxies[i + 1..].iter() {
+                if aabb_b.min.x > aabb_a.max.x {
+                    break;
+                }
+                if aabb_a.min.y <= aabb_b.max.y && aabb_b.min.y <= aabb_a.max.y {
+                    pairs.push((*entity_a, *entity_b));
+                }
+            }
+        }
+    }
+}
+
+/// Uniform grid that buckets bodies by the cells their bounding boxes overlap.
+#[derive(Debug, Resource)]
+pub struct SpatialHash {
+    pub cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Entity, Aabb)>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+        }
+    }
+
+    fn cell(&self, point: Vec2) -> IVec2 {
+        (point / self.cell_size).floor().as_ivec2()
+    }
+}
+
+impl Default for SpatialHash {
+    fn default() -> Self {
+        Self::new(1.)
+    }
+}
+
+impl Broadphase for SpatialHash {
+    fn build(&mut self, proxies: &[(Entity, Aabb)]) {
+        // Keep the buckets that were used last step around to avoid reallocating them.
+        self.cells.retain(|_, bucket| {
+            let keep = !bucket.is_empty();
+            bucket.clear();
+            keep
+        });
+        for (entity, aabb) in proxies.iter() {
+            let min_cell = self.cell(aabb.min);
+            let max_cell = self.cell(aabb.max);
+            for x in min_cell.x..=max_cell.x {
+                for y in min_cell.y..=max_cell.y {
+                    self.cells
+                        .entry(IVec2::new(x, y))
+                        .or_default()
+                        .push((*entity, *aabb));
+                }
+            }
+        }
+    }
+
+    fn query_pairs(&self, pairs: &mut Vec<(Entity, Entity)>) {
+        for (cell, bucket) in self.cells.iter() {
+            for (i, (entity_a, aabb_a)) in bucket.iter().enumerate() {
+                for (entity_b, aabb_b) in bucket[i + 1..].iter() {
+                    if !aabb_a.intersects(aabb_b) {
+                        continue;
+                    }
+                    // Bodies spanning several cells share more than one bucket, only report
+                    // the pair from the cell holding the corner of their overlap.
+                    if self.cell(aabb_a.min.max(aabb_b.min)) != *cell {
+                        continue;
+                    }
+                    pairs.push((*entity_a, *entity_b));
+                }
+            }
+        }
+    }
+}
+
+const NULL_NODE: usize = usize::MAX;
+
+#[derive(Clone, Debug)]
+struct TreeNode {
+    aabb: Aabb,
+    parent: usize,
+    children: [usize; 2],
+    /// Longest path down to a leaf, zero for leaves.
+    height: u32,
+    /// Only set on leaves.
+    entity: Option<Entity>,
+}
+
+impl TreeNode {
+    fn is_leaf(&self) -> bool {
+        self.entity.is_some()
+    }
+}
+
+/// Bounding volume hierarchy over fattened bounding boxes.
+///
+/// Leaves are only reinserted once a body leaves its fat box, so bodies at rest and statics
+/// cost nothing to update, and huge and tiny colliders can be mixed freely. Besides finding
+/// collision pairs, the tree answers [`query_aabb`](Self::query_aabb) and
+/// [`query_point`](Self::query_point).
+#[derive(Debug, Resource)]
+pub struct DynamicAabbTree {
+    /// How much the stored boxes are grown past the collider bounds.
+    pub margin: f32,
+    nodes: Vec<TreeNode>,
+    free_nodes: Vec<usize>,
+    root: usize,
+    leaves: HashMap<Entity, usize>,
+}
+
+impl DynamicAabbTree {
+    pub fn new(margin: f32) -> Self {
+        Self {
+            margin,
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            root: NULL_NODE,
+            leaves: HashMap::default(),
+        }
+    }
+
+    /// Calls `callback` with every entity whose fat bounding box overlaps `aabb`.
+    pub fn query_aabb(&self, aabb: &Aabb, mut callback: impl FnMut(Entity)) {
+        self.traverse(
+            &mut Vec::new(),
+            |node| node.aabb.intersects(aabb),
+            |_, entity| callback(entity),
+        );
+    }
+
+    /// Calls `callback` with every entity whose fat bounding box contains `point`.
+    pub fn query_point(&self, point: Vec2, mut callback: impl FnMut(Entity)) {
+        self.traverse(
+            &mut Vec::new(),
+            |node| node.aabb.contains_point(point),
+            |_, entity| callback(entity),
+        );
+    }
+
+    /// Visits the leaves under the nodes that `overlaps` accepts, using `stack` as scratch space
+    /// so repeated queries can share it.
+    fn traverse(
+        &self,
+        stack: &mut Vec<usize>,
+        mut overlaps: impl FnMut(&TreeNode) -> bool,
+        mut on_leaf: impl FnMut(usize, Entity),
+    ) {
+        stack.clear();
+        if self.root == NULL_NODE {
+            return;
+        }
+        stack.push(self.root);
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !overlaps(node) {
+                continue;
+            }
+            match node.entity {
+                Some(entity) => on_leaf(index, entity),
+                None => stack.extend(node.children),
+            }
+        }
+    }
+
+    fn allocate_node(&mut self, node: TreeNode) -> usize {
+        match self.free_nodes.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn insert_leaf(&mut self, entity: Entity, aabb: Aabb) {
+        let leaf = self.allocate_node(TreeNode {
+            aabb,
+            parent: NULL_NODE,
+            children: [NULL_NODE; 2],
+            height: 0,
+            entity: Some(entity),
+        });
+        self.leaves.insert(entity, leaf);
+
+        if self.root == NULL_NODE {
+            self.root = leaf;
+            return;
+        }
+
+        // Walk down towards the sibling that grows the total perimeter the least.
+        let mut sibling = self.root;
+        while !self.nodes[sibling].is_leaf() {
+            let node = &self.nodes[sibling];
+            let combined = node.aabb.merge(&aabb).perimeter();
+            // Cost of making a new parent for this node and the leaf.
+            let cost = 2. * combined;
+            // Everything below has to grow to fit the leaf either way.
+            let inherited_cost = 2. * (combined - node.aabb.perimeter());
+            let child_cost = |child: &TreeNode| {
+                let grown = child.aabb.merge(&aabb).perimeter();
+                if child.is_leaf() {
+                    grown + inherited_cost
+                } else {
+                    grown - child.aabb.perimeter() + inherited_cost
+                }
+            };
+            let [left, right] = node.children;
+            let cost_left = child_cost(&self.nodes[left]);
+            let cost_right = child_cost(&self.nodes[right]);
+            if cost < cost_left && cost < cost_right {
+                break;
+            }
+            sibling = if cost_left < cost_right { left } else { right };
+        }
+
+        let old_parent = self.nodes[sibling].parent;
+        let new_parent = self.allocate_node(TreeNode {
+            aabb: self.nodes[sibling].aabb.merge(&aabb),
+            parent: old_parent,
+            children: [sibling, leaf],
+            height: 0,
+            entity: None,
+        });
+        self.nodes[sibling].parent = new_parent;
+        self.nodes[leaf].parent = new_parent;
+        if old_parent == NULL_NODE {
+            self.root = new_parent;
+        } else {
+            let children = &mut self.nodes[old_parent].children;
+            let slot = if children[0] == sibling { 0 } else { 1 };
+            children[slot] = new_parent;
+        }
+        self.refit(new_parent);
+    }
+
+    fn remove_leaf(&mut self, entity: Entity) {
+        let Some(leaf) = self.leaves.remove(&entity) else {
+            return;
+        };
+        self.free_nodes.push(leaf);
+        if leaf == self.root {
+            self.root = NULL_NODE;
+            return;
+        }
+
+        // The sibling takes the place of the parent.
+        let parent = self.nodes[leaf].parent;
+        let grandparent = self.nodes[parent].parent;
+        let [left, right] = self.nodes[parent].children;
+        let sibling = if left == leaf { right } else { left };
+        self.free_nodes.push(parent);
+        self.nodes[sibling].parent = grandparent;
+        if grandparent == NULL_NODE {
+            self.root = sibling;
+        } else {
+            let children = &mut self.nodes[grandparent].children;
+            let slot = if children[0] == parent { 0 } else { 1 };
+            children[slot] = sibling;
+            self.refit(grandparent);
+        }
+    }
+
+    /// Rebalances `index` and all of its ancestors, recomputing their boxes and heights.
+    fn refit(&mut self, mut index: usize) {
+        while index != NULL_NODE {
+            index = self.balance(index);
+            self.fit(index);
+            index = self.nodes[index].parent;
+        }
+    }
+
+    /// Recomputes the box and height of an internal node from its children.
+    fn fit(&mut self, index: usize) {
+        let [left, right] = self.nodes[index].children;
+        let (left, right) = (&self.nodes[left], &self.nodes[right]);
+        let aabb = left.aabb.merge(&right.aabb);
+        let height = 1 + left.height.max(right.height);
+        let node = &mut self.nodes[index];
+        node.aabb = aabb;
+        node.height = height;
+    }
+
+    /// AVL rotation that lifts the taller child of `index` into its place when the heights of
+    /// the two children differ by more than one. Returns the node now in that place.
+    fn balance(&mut self, index: usize) -> usize {
+        let node = &self.nodes[index];
+        if node.is_leaf() {
+            return index;
+        }
+        let [left, right] = node.children;
+        let (left_height, right_height) = (self.nodes[left].height, self.nodes[right].height);
+        let taller = if right_height > left_height + 1 {
+            1
+        } else if left_height > right_height + 1 {
+            0
+        } else {
+            return index;
+        };
+
+        // The taller child becomes the parent of `index`, keeping its own taller child and
+        // handing the other one down to take its old slot
+        let up = node.children[taller];
+        let parent = node.parent;
+        let [first, second] = self.nodes[up].children;
+        let (kept, given) = if self.nodes[first].height > self.nodes[second].height {
+            (first, second)
+        } else {
+            (second, first)
+        };
+        self.nodes[up].children = [index, kept];
+        self.nodes[up].parent = parent;
+        self.nodes[index].parent = up;
+        self.nodes[index].children[taller] = given;
+        self.nodes[given].parent = index;
+        if parent == NULL_NODE {
+            self.root = up;
+        } else {
+            let children = &mut self.nodes[parent].children;
+            let slot = if children[0] == index { 0 } else { 1 };
+            children[slot] = up;
+        }
+        self.fit(index);
+        self.fit(up);
+        up
+    }
+}
+
+impl Default for DynamicAabbTree {
+    fn default() -> Self {
+        Self::new(0.1)
+    }
+}
+
+impl Broadphase for DynamicAabbTree {
+    fn build(&mut self, proxies: &[(Entity, Aabb)]) {
+        self.nodes.clear();
+        self.free_nodes.clear();
+        self.root = NULL_NODE;
+        self.leaves.clear();
+        for (entity, aabb) in proxies.iter() {
+            self.insert_leaf(*entity, aabb.grow(self.margin));
+        }
+    }
+
+    fn update(&mut self, proxies: &[(Entity, Aabb)]) {
+        let mut removed: HashSet<Entity> = self.leaves.keys().copied().collect();
+        for (entity, aabb) in proxies.iter() {
+            removed.remove(entity);
+            if let Some(&leaf) = self.leaves.get(entity) {
+                if self.nodes[leaf].aabb.contains(aabb) {
+                    continue;
+                }
+                self.remove_leaf(*entity);
+            }
+            self.insert_leaf(*entity, aabb.grow(self.margin));
+        }
+        for entity in removed {
+            self.remove_leaf(entity);
+        }
+    }
+
+    fn query_pairs(&self, pairs: &mut Vec<(Entity, Entity)>) {
+        let mut stack = Vec::new();
+        for (&entity_a, &leaf_a) in self.leaves.iter() {
+            let aabb = self.nodes[leaf_a].aabb;
+            self.traverse(
+                &mut stack,
+                |node| node.aabb.intersects(&aabb),
+                |leaf_b, entity_b| {
+                    // Each pair is found from both leaves, keep only one of them.
+                    if leaf_a < leaf_b {
+                        pairs.push((entity_a, entity_b));
+                    }
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Boxes of mixed sizes, some overlapping and some far apart.
+    fn proxies() -> Vec<(Entity, Aabb)> {
+        (0..200)
+            .map(|i| {
+                let center = Vec2::new((i * 37 % 101) as f32 * 0.3, (i * 53 % 89) as f32 * 0.3);
+                let half_extents = Vec2::new(0.2 + (i % 5) as f32 * 0.3, 0.1 + (i % 3) as f32);
+                (
+                    Entity::from_raw(i),
+                    Aabb::from_center_half_extents(center, half_extents),
+                )
+            })
+            .collect()
+    }
+
+    fn sorted_pairs(broadphase: &impl Broadphase) -> Vec<(Entity, Entity)> {
+        let mut pairs = Vec::new();
+        broadphase.query_pairs(&mut pairs);
+        let mut pairs: Vec<_> = pairs
+            .into_iter()
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect();
+        pairs.sort();
+        pairs
+    }
+
+    #[test]
+    fn broadphases_find_the_same_pairs() {
+        let proxies = proxies();
+        let mut brute_force = BruteForce::default();
+        brute_force.build(&proxies);
+        let expected = sorted_pairs(&brute_force);
+        assert!(!expected.is_empty());
+
+        let mut sweep_and_prune = SweepAndPrune::default();
+        sweep_and_prune.build(&proxies);
+        assert_eq!(sorted_pairs(&sweep_and_prune), expected);
+        let mut spatial_hash = SpatialHash::new(1.);
+        spatial_hash.build(&proxies);
+        assert_eq!(sorted_pairs(&spatial_hash), expected);
+        // Without a margin the fat boxes are the bounding boxes themselves
+        let mut tree = DynamicAabbTree::new(0.);
+        tree.build(&proxies);
+        assert_eq!(sorted_pairs(&tree), expected);
+    }
+
+    #[test]
+    fn tree_stays_balanced() {
+        let mut tree = DynamicAabbTree::new(0.);
+        // Boxes in a row are the worst case for an unbalanced tree
+        let proxies: Vec<_> = (0..256)
+            .map(|i| {
+                let center = Vec2::new(i as f32, 0.);
+                (
+                    Entity::from_raw(i),
+                    Aabb::from_center_half_extents(center, Vec2::splat(0.4)),
+                )
+            })
+            .collect();
+        tree.build(&proxies);
+        assert!(tree.nodes[tree.root].height <= 12);
+
+        // Move every other box so half of them are reinserted
+        let moved: Vec<_> = proxies
+            .iter()
+            .map(|(entity, aabb)| {
+                let offset = (entity.index() % 2) as f32 * 300.;
+                (*entity, Aabb::new(aabb.min + offset, aabb.max + offset))
+            })
+            .collect();
+        tree.update(&moved);
+        assert!(tree.nodes[tree.root].height <= 12);
+        for (&entity, &leaf) in tree.leaves.iter() {
+            let mut index = leaf;
+            while tree.nodes[index].parent != NULL_NODE {
+                let parent = &tree.nodes[tree.nodes[index].parent];
+                assert!(parent.children.contains(&index));
+                assert!(parent.aabb.contains(&tree.nodes[index].aabb));
+                assert!(parent.height > tree.nodes[index].height);
+                index = tree.nodes[index].parent;
+            }
+            assert_eq!(index, tree.root, "{entity:?} is not under the root");
+        }
+        let mut found = Vec::new();
+        tree.query_point(Vec2::new(301., 300.), |entity| found.push(entity));
+        assert_eq!(found, [Entity::from_raw(1)]);
+    }
+}
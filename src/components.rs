@@ -1,5 +1,9 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+
 use bevy::prelude::*;
 
+use crate::narrowphase::polygon_area;
+
 #[derive(Component, Default)]
 pub struct Ball;
 
@@ -10,7 +14,33 @@ pub struct Pos(pub Vec2);
 pub struct PrevPos(pub Vec2);
 
 #[derive(Component, Debug)]
-pub struct Velocity(pub(crate) Vec2);
+pub struct Velocity(pub Vec2);
+
+/// Orientation in radians, counter-clockwise.
+#[derive(Component, Debug, Default)]
+pub struct Rot(pub f32);
+
+#[derive(Component, Debug, Default)]
+pub struct PrevRot(pub f32);
+
+#[derive(Component, Debug, Default)]
+pub struct AngularVelocity(pub f32);
+
+#[derive(Component, Debug, Default)]
+pub struct PreSolveAngularVelocity(pub(crate) f32);
+
+/// Moment of inertia, recomputed from the `Mass` and the collider whenever one of them changes.
+#[derive(Component, Debug)]
+pub struct Inertia(pub f32);
+
+impl Default for Inertia {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+#[derive(Component, Debug, Default)]
+pub struct ExternalTorque(pub f32);
 
 #[derive(Component)]
 pub struct Mass(pub f32);
@@ -21,6 +51,15 @@ impl Default for Mass {
     }
 }
 
+/// Inverse of the `Mass` the solver pushes with, kept up to date from the `RigidBody` and the
+/// `Mass`. Static and kinematic bodies have zero, which makes them immovable.
+#[derive(Component, Debug, Default)]
+pub struct InverseMass(pub f32);
+
+/// Inverse of the `Inertia`, zero for static and kinematic bodies like the [`InverseMass`].
+#[derive(Component, Debug, Default)]
+pub struct InverseInertia(pub f32);
+
 #[derive(Component, Debug)]
 pub struct CircleCollider {
     pub radius: f32,
@@ -44,6 +83,22 @@ impl Default for Restitution {
     }
 }
 
+/// Coulomb friction, the coefficients of two touching bodies are averaged.
+#[derive(Component, Debug)]
+pub struct Friction {
+    pub static_coefficient: f32,
+    pub dynamic_coefficient: f32,
+}
+
+impl Default for Friction {
+    fn default() -> Self {
+        Self {
+            static_coefficient: 0.4,
+            dynamic_coefficient: 0.3,
+        }
+    }
+}
+
 #[derive(Component, Debug)]
 pub struct BoxCollider {
     pub size: Vec2,
@@ -54,3 +109,150 @@ impl Default for BoxCollider {
         Self { size: Vec2::ONE }
     }
 }
+
+/// Rounded segment along the local y axis, `half_height` is the distance from the center to the
+/// center of either cap.
+#[derive(Component, Debug)]
+pub struct CapsuleCollider {
+    pub half_height: f32,
+    pub radius: f32,
+}
+
+impl Default for CapsuleCollider {
+    fn default() -> Self {
+        Self {
+            half_height: 0.5,
+            radius: 0.25,
+        }
+    }
+}
+
+/// Convex polygon given by its vertices in counter-clockwise order, relative to the center of
+/// mass of the body.
+#[derive(Component, Debug, Clone)]
+pub struct ConvexPolygonCollider {
+    pub vertices: Vec<Vec2>,
+}
+
+impl ConvexPolygonCollider {
+    /// Takes the vertices in either winding order and shifts them so their centroid, the center
+    /// of mass of a uniform body, is at the origin. Repeated vertices are dropped.
+    pub fn new(mut vertices: Vec<Vec2>) -> Self {
+        vertices.dedup_by(|a, b| a.distance_squared(*b) <= f32::EPSILON);
+        while vertices.len() > 1
+            && vertices[0].distance_squared(vertices[vertices.len() - 1]) <= f32::EPSILON
+        {
+            vertices.pop();
+        }
+        let area = polygon_area(&vertices);
+        if area < 0. {
+            vertices.reverse();
+        }
+
+        let n = vertices.len();
+        if area.abs() > f32::EPSILON {
+            let centroid = (0..n)
+                .map(|i| {
+                    let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+                    (a + b) * a.perp_dot(b)
+                })
+                .sum::<Vec2>()
+                / (6. * area.abs());
+            for vertex in vertices.iter_mut() {
+                *vertex -= centroid;
+            }
+        }
+        debug_assert!(
+            (0..n).all(|i| {
+                let edge = vertices[(i + 1) % n] - vertices[i];
+                let next_edge = vertices[(i + 2) % n] - vertices[(i + 1) % n];
+                edge.perp_dot(next_edge) >= -1e-4 * edge.length() * next_edge.length()
+            }),
+            "polygon collider is not convex: {vertices:?}"
+        );
+        Self { vertices }
+    }
+
+    /// Polygon with `sides` equal sides and a vertex at the top, `radius` away from the center.
+    pub fn regular(sides: usize, radius: f32) -> Self {
+        let vertices = (0..sides)
+            .map(|i| Vec2::from_angle(FRAC_PI_2 + i as f32 * TAU / sides as f32) * radius)
+            .collect();
+        Self::new(vertices)
+    }
+}
+
+impl Default for ConvexPolygonCollider {
+    fn default() -> Self {
+        Self::new(vec![
+            Vec2::new(-0.5, -0.5),
+            Vec2::new(0.5, -0.5),
+            Vec2::new(0.5, 0.5),
+            Vec2::new(-0.5, 0.5),
+        ])
+    }
+}
+
+/// Chain of line segments for static terrain, relative to the center of the body.
+///
+/// Segments are one-sided: bodies collide with the side to the right of the direction the
+/// vertices run in, and a segment leaves alone the bodies whose center is behind it. That is
+/// the outside of a counter-clockwise loop, and ground running from right to left faces up.
+/// Fewer than two vertices collide with nothing.
+#[derive(Component, Debug, Clone)]
+pub struct PolylineCollider {
+    pub vertices: Vec<Vec2>,
+    /// Joins the last vertex back to the first one.
+    pub closed: bool,
+}
+
+impl PolylineCollider {
+    pub fn new(vertices: Vec<Vec2>) -> Self {
+        Self {
+            vertices,
+            closed: false,
+        }
+    }
+
+    pub fn new_closed(vertices: Vec<Vec2>) -> Self {
+        Self {
+            vertices,
+            closed: true,
+        }
+    }
+}
+
+impl Default for PolylineCollider {
+    fn default() -> Self {
+        Self::new(vec![Vec2::new(0.5, 0.), Vec2::new(-0.5, 0.)])
+    }
+}
+
+/// Bodies in the same group don't collide with each other, like the links of a rope.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionGroup(pub Entity);
+
+/// Particle that is solved as part of a fluid rather than as a marble, see [`crate::fluid`].
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct FluidParticle;
+
+/// How the solver treats a body. Switching it at runtime starts the body from where it is.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RigidBody {
+    /// Moved by gravity and collisions, needs a `Mass`.
+    #[default]
+    Dynamic,
+    /// Never moves, bodies that collide with it get an infinite mass to push against.
+    Static,
+    /// Moves with its `Velocity` and `AngularVelocity`, like a moving platform or a door.
+    ///
+    /// Gameplay code sets its velocities, or its position to teleport it. Other bodies can't push
+    /// it around, but they are pushed and carried along by it.
+    Kinematic,
+}
+
+impl RigidBody {
+    pub fn is_dynamic(self) -> bool {
+        self == Self::Dynamic
+    }
+}
@@ -0,0 +1,131 @@
+use bevy::{ecs::query::QueryData, prelude::*};
+
+use crate::components::*;
+use crate::resources::SubDeltaTime;
+use crate::solver::PoseBody;
+
+/// Constraint between two bodies that is solved on their positions and orientations once per
+/// substep, after the contacts.
+///
+/// Implementors keep their own Lagrange multipliers and accumulate into them with
+/// [`apply_positional_constraint`](crate::solver::apply_positional_constraint) and
+/// [`apply_angular_constraint`](crate::solver::apply_angular_constraint). Register them with
+/// [`XPBDPlugin::with_constraint`](crate::XPBDPlugin::with_constraint).
+pub trait PositionConstraint: Component {
+    /// Bodies the constraint acts on, either of them may be static.
+    fn entities(&self) -> [Entity; 2];
+
+    /// Called at the start of every substep.
+    fn clear_lagrange_multipliers(&mut self);
+
+    /// Moves the bodies towards satisfying the constraint.
+    fn solve(&mut self, body_a: &mut PoseBody, body_b: &mut PoseBody, dt: f32);
+
+    /// Force the constraint applied on the first body in the last substep, its Lagrange
+    /// multipliers divided by the substep squared. The second body got the opposite force.
+    fn force(&self) -> Vec2;
+
+    /// Torque the constraint applied on the first body in the last substep, not counting the
+    /// torque of `force` around the center. The second body got the opposite torque.
+    fn torque(&self) -> f32;
+}
+
+/// Removes the constraint on the same entity once the force it applies exceeds this many
+/// newtons, sending a [`JointBroken`] event.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BreakForce(pub f32);
+
+/// Removes the constraint on the same entity once the torque it applies exceeds this many
+/// newton metres, sending a [`JointBroken`] event. Works alone or next to a [`BreakForce`].
+#[derive(Component, Debug, Clone, Copy)]
+pub struct BreakTorque(pub f32);
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct JointBroken {
+    /// Entity that held the constraint.
+    pub joint: Entity,
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+    /// Force the constraint applied in its last substep.
+    pub force: Vec2,
+    /// Torque the constraint applied in its last substep.
+    pub torque: f32,
+}
+
+pub(crate) fn clear_lagrange_multipliers<C: PositionConstraint>(mut constraints: Query<&mut C>) {
+    for mut constraint in constraints.iter_mut() {
+        constraint.clear_lagrange_multipliers();
+    }
+}
+
+/// Pose and mass of a body a constraint acts on.
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub(crate) struct PoseQuery {
+    pos: &'static mut Pos,
+    prev_pos: Option<&'static PrevPos>,
+    rot: &'static mut Rot,
+    prev_rot: Option<&'static PrevRot>,
+    inverse_mass: Option<&'static InverseMass>,
+    inverse_inertia: Option<&'static InverseInertia>,
+}
+
+impl PoseQueryItem<'_> {
+    /// Points without an inverse mass, like the anchors of pinned joints, are static.
+    pub(crate) fn pose_body(&mut self) -> PoseBody<'_> {
+        PoseBody {
+            prev_pos: self.prev_pos.map_or(self.pos.0, |prev_pos| prev_pos.0),
+            prev_rot: self.prev_rot.map_or(self.rot.0, |prev_rot| prev_rot.0),
+            start_rot: self.rot.0,
+            pos: &mut self.pos.0,
+            rot: &mut self.rot.0,
+            inverse_mass: self.inverse_mass.map_or(0., |inverse_mass| inverse_mass.0),
+            inverse_inertia: self
+                .inverse_inertia
+                .map_or(0., |inverse_inertia| inverse_inertia.0),
+        }
+    }
+}
+
+pub(crate) fn solve_constraints<C: PositionConstraint>(
+    mut constraints: Query<&mut C>,
+    mut bodies: Query<PoseQuery>,
+    sub_dt: Res<SubDeltaTime>,
+) {
+    for mut constraint in constraints.iter_mut() {
+        let Ok([mut body_a, mut body_b]) = bodies.get_many_mut(constraint.entities()) else {
+            continue;
+        };
+        constraint.solve(&mut body_a.pose_body(), &mut body_b.pose_body(), sub_dt.0);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn break_constraints<C: PositionConstraint>(
+    constraints: Query<
+        (Entity, &C, Option<&BreakForce>, Option<&BreakTorque>),
+        Or<(With<BreakForce>, With<BreakTorque>)>,
+    >,
+    mut commands: Commands,
+    mut joint_broken: EventWriter<JointBroken>,
+) {
+    for (joint, constraint, break_force, break_torque) in constraints.iter() {
+        let force = constraint.force();
+        let torque = constraint.torque();
+        let overloaded = break_force.is_some_and(|break_force| force.length() > break_force.0)
+            || break_torque.is_some_and(|break_torque| torque.abs() > break_torque.0);
+        if overloaded {
+            commands
+                .entity(joint)
+                .remove::<(C, BreakForce, BreakTorque)>();
+            let [entity_a, entity_b] = constraint.entities();
+            joint_broken.send(JointBroken {
+                joint,
+                entity_a,
+                entity_b,
+                force,
+                torque,
+            });
+        }
+    }
+}
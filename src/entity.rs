@@ -1,56 +1,95 @@
 use bevy::prelude::*;
 use crate::components::*;
-use crate::DELTA_TIME;
+use crate::fluid::FluidSettings;
+use crate::joints::{DistanceJoint, MaxStretch, RevoluteJoint};
+use crate::narrowphase::polygon_area;
+use crate::softbody::{AreaConstraint, ShapeMatching};
 
 
 #[derive(Bundle)]
 pub struct ParticleBundle {
     pub ball: Ball,
+    pub rigid_body: RigidBody,
     pub pos: Pos,
     pub prev_pos: PrevPos,
+    pub rot: Rot,
+    pub prev_rot: PrevRot,
     pub mass: Mass,
+    pub inertia: Inertia,
+    pub inverse_mass: InverseMass,
+    pub inverse_inertia: InverseInertia,
     pub collider: CircleCollider,
     pub vel: Velocity,
     pub pre_solve_vel: PreSolveVel,
-    pub restitution: Restitution
+    pub ang_vel: AngularVelocity,
+    pub pre_solve_ang_vel: PreSolveAngularVelocity,
+    pub restitution: Restitution,
+    pub friction: Friction,
 }
 
 impl ParticleBundle {
     pub fn new_with_pos_and_vel(pos: Vec2, vel: Vec2) -> Self {
         Self {
             ball: Ball,
+            rigid_body: RigidBody::Dynamic,
             pos: Pos(pos),
-            prev_pos: PrevPos(pos - vel * DELTA_TIME),
+            prev_pos: PrevPos(pos),
+            rot: Rot::default(),
+            prev_rot: PrevRot::default(),
             mass: Mass::default(),
+            inertia: Inertia::default(),
+            inverse_mass: InverseMass::default(),
+            inverse_inertia: InverseInertia::default(),
             collider: CircleCollider::default(),
             vel: Velocity(vel),
             pre_solve_vel: PreSolveVel::default(),
-            restitution: Restitution::default()
+            ang_vel: AngularVelocity::default(),
+            pre_solve_ang_vel: PreSolveAngularVelocity::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
         }
     }
     pub fn new_with_pos_and_vel_and_mass(pos: Vec2, vel: Vec2, mass: f32) -> Self {
         Self {
             ball: Ball,
+            rigid_body: RigidBody::Dynamic,
             pos: Pos(pos),
-            prev_pos: PrevPos(pos - vel * DELTA_TIME),
+            prev_pos: PrevPos(pos),
+            rot: Rot::default(),
+            prev_rot: PrevRot::default(),
             mass: Mass(mass),
+            inertia: Inertia::default(),
+            inverse_mass: InverseMass::default(),
+            inverse_inertia: InverseInertia::default(),
             collider: CircleCollider::default(),
             vel: Velocity(vel),
             pre_solve_vel: PreSolveVel::default(),
-            restitution: Restitution::default()
+            ang_vel: AngularVelocity::default(),
+            pre_solve_ang_vel: PreSolveAngularVelocity::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
         }
     }
 
     pub fn new_with_pos_and_vel_and_mass_and_collider(pos: Vec2, vel: Vec2, mass: f32, collider_radius: f32) -> Self {
         Self {
             ball: Ball,
+            rigid_body: RigidBody::Dynamic,
             pos: Pos(pos),
-            prev_pos: PrevPos(pos - vel * DELTA_TIME),
+            prev_pos: PrevPos(pos),
+            rot: Rot::default(),
+            prev_rot: PrevRot::default(),
             mass: Mass(mass),
+            inertia: Inertia::default(),
+            inverse_mass: InverseMass::default(),
+            inverse_inertia: InverseInertia::default(),
             collider: CircleCollider {radius: collider_radius},
             vel: Velocity(vel),
             pre_solve_vel: PreSolveVel::default(),
-            restitution: Restitution::default()
+            ang_vel: AngularVelocity::default(),
+            pre_solve_ang_vel: PreSolveAngularVelocity::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
         }
     }
 }
@@ -61,16 +100,687 @@ impl Default for ParticleBundle {
     }
 }
 
-#[derive(Bundle, Default)]
+#[derive(Bundle)]
 pub struct StaticCircleBundle {
+    pub rigid_body: RigidBody,
     pub pos: Pos,
+    pub rot: Rot,
     pub collider: CircleCollider,
     pub restitution: Restitution,
+    pub friction: Friction,
 }
 
-#[derive(Bundle, Default)]
+impl Default for StaticCircleBundle {
+    fn default() -> Self {
+        Self {
+            rigid_body: RigidBody::Static,
+            pos: Pos::default(),
+            rot: Rot::default(),
+            collider: CircleCollider::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
+        }
+    }
+}
+
+#[derive(Bundle)]
 pub struct StaticBoxBundle {
+    pub rigid_body: RigidBody,
     pub pos: Pos,
+    pub rot: Rot,
     pub collider: BoxCollider,
     pub restitution: Restitution,
+    pub friction: Friction,
+}
+
+impl Default for StaticBoxBundle {
+    fn default() -> Self {
+        Self {
+            rigid_body: RigidBody::Static,
+            pos: Pos::default(),
+            rot: Rot::default(),
+            collider: BoxCollider::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct StaticCapsuleBundle {
+    pub rigid_body: RigidBody,
+    pub pos: Pos,
+    pub rot: Rot,
+    pub collider: CapsuleCollider,
+    pub restitution: Restitution,
+    pub friction: Friction,
+}
+
+impl Default for StaticCapsuleBundle {
+    fn default() -> Self {
+        Self {
+            rigid_body: RigidBody::Static,
+            pos: Pos::default(),
+            rot: Rot::default(),
+            collider: CapsuleCollider::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct StaticConvexPolygonBundle {
+    pub rigid_body: RigidBody,
+    pub pos: Pos,
+    pub rot: Rot,
+    pub collider: ConvexPolygonCollider,
+    pub restitution: Restitution,
+    pub friction: Friction,
+}
+
+impl Default for StaticConvexPolygonBundle {
+    fn default() -> Self {
+        Self {
+            rigid_body: RigidBody::Static,
+            pos: Pos::default(),
+            rot: Rot::default(),
+            collider: ConvexPolygonCollider::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
+        }
+    }
+}
+
+/// Terrain made of one-sided segments, see [`PolylineCollider`].
+#[derive(Bundle)]
+pub struct StaticPolylineBundle {
+    pub rigid_body: RigidBody,
+    pub pos: Pos,
+    pub rot: Rot,
+    pub collider: PolylineCollider,
+    pub restitution: Restitution,
+    pub friction: Friction,
+}
+
+impl Default for StaticPolylineBundle {
+    fn default() -> Self {
+        Self {
+            rigid_body: RigidBody::Static,
+            pos: Pos::default(),
+            rot: Rot::default(),
+            collider: PolylineCollider::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct DynamicBoxBundle {
+    pub rigid_body: RigidBody,
+    pub pos: Pos,
+    pub prev_pos: PrevPos,
+    pub rot: Rot,
+    pub prev_rot: PrevRot,
+    pub mass: Mass,
+    pub inertia: Inertia,
+    pub inverse_mass: InverseMass,
+    pub inverse_inertia: InverseInertia,
+    pub collider: BoxCollider,
+    pub vel: Velocity,
+    pub pre_solve_vel: PreSolveVel,
+    pub ang_vel: AngularVelocity,
+    pub pre_solve_ang_vel: PreSolveAngularVelocity,
+    pub restitution: Restitution,
+    pub friction: Friction,
+}
+
+impl DynamicBoxBundle {
+    pub fn new_with_pos_and_vel(pos: Vec2, vel: Vec2) -> Self {
+        Self {
+            rigid_body: RigidBody::Dynamic,
+            pos: Pos(pos),
+            prev_pos: PrevPos(pos),
+            rot: Rot::default(),
+            prev_rot: PrevRot::default(),
+            mass: Mass::default(),
+            inertia: Inertia::default(),
+            inverse_mass: InverseMass::default(),
+            inverse_inertia: InverseInertia::default(),
+            collider: BoxCollider::default(),
+            vel: Velocity(vel),
+            pre_solve_vel: PreSolveVel::default(),
+            ang_vel: AngularVelocity::default(),
+            pre_solve_ang_vel: PreSolveAngularVelocity::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
+        }
+    }
+}
+
+impl Default for DynamicBoxBundle {
+    fn default() -> Self {
+        Self::new_with_pos_and_vel(Vec2::ZERO, Vec2::ZERO)
+    }
+}
+
+#[derive(Bundle)]
+pub struct DynamicCapsuleBundle {
+    pub rigid_body: RigidBody,
+    pub pos: Pos,
+    pub prev_pos: PrevPos,
+    pub rot: Rot,
+    pub prev_rot: PrevRot,
+    pub mass: Mass,
+    pub inertia: Inertia,
+    pub inverse_mass: InverseMass,
+    pub inverse_inertia: InverseInertia,
+    pub collider: CapsuleCollider,
+    pub vel: Velocity,
+    pub pre_solve_vel: PreSolveVel,
+    pub ang_vel: AngularVelocity,
+    pub pre_solve_ang_vel: PreSolveAngularVelocity,
+    pub restitution: Restitution,
+    pub friction: Friction,
+}
+
+impl DynamicCapsuleBundle {
+    pub fn new_with_pos_and_vel(pos: Vec2, vel: Vec2) -> Self {
+        Self {
+            rigid_body: RigidBody::Dynamic,
+            pos: Pos(pos),
+            prev_pos: PrevPos(pos),
+            rot: Rot::default(),
+            prev_rot: PrevRot::default(),
+            mass: Mass::default(),
+            inertia: Inertia::default(),
+            inverse_mass: InverseMass::default(),
+            inverse_inertia: InverseInertia::default(),
+            collider: CapsuleCollider::default(),
+            vel: Velocity(vel),
+            pre_solve_vel: PreSolveVel::default(),
+            ang_vel: AngularVelocity::default(),
+            pre_solve_ang_vel: PreSolveAngularVelocity::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
+        }
+    }
+}
+
+impl Default for DynamicCapsuleBundle {
+    fn default() -> Self {
+        Self::new_with_pos_and_vel(Vec2::ZERO, Vec2::ZERO)
+    }
+}
+
+#[derive(Bundle)]
+pub struct DynamicConvexPolygonBundle {
+    pub rigid_body: RigidBody,
+    pub pos: Pos,
+    pub prev_pos: PrevPos,
+    pub rot: Rot,
+    pub prev_rot: PrevRot,
+    pub mass: Mass,
+    pub inertia: Inertia,
+    pub inverse_mass: InverseMass,
+    pub inverse_inertia: InverseInertia,
+    pub collider: ConvexPolygonCollider,
+    pub vel: Velocity,
+    pub pre_solve_vel: PreSolveVel,
+    pub ang_vel: AngularVelocity,
+    pub pre_solve_ang_vel: PreSolveAngularVelocity,
+    pub restitution: Restitution,
+    pub friction: Friction,
+}
+
+impl DynamicConvexPolygonBundle {
+    pub fn new_with_pos_and_vel(pos: Vec2, vel: Vec2) -> Self {
+        Self {
+            rigid_body: RigidBody::Dynamic,
+            pos: Pos(pos),
+            prev_pos: PrevPos(pos),
+            rot: Rot::default(),
+            prev_rot: PrevRot::default(),
+            mass: Mass::default(),
+            inertia: Inertia::default(),
+            inverse_mass: InverseMass::default(),
+            inverse_inertia: InverseInertia::default(),
+            collider: ConvexPolygonCollider::default(),
+            vel: Velocity(vel),
+            pre_solve_vel: PreSolveVel::default(),
+            ang_vel: AngularVelocity::default(),
+            pre_solve_ang_vel: PreSolveAngularVelocity::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
+        }
+    }
+}
+
+impl Default for DynamicConvexPolygonBundle {
+    fn default() -> Self {
+        Self::new_with_pos_and_vel(Vec2::ZERO, Vec2::ZERO)
+    }
+}
+
+/// Box moved by gameplay code through its velocities, see [`RigidBody::Kinematic`].
+#[derive(Bundle)]
+pub struct KinematicBoxBundle {
+    pub rigid_body: RigidBody,
+    pub pos: Pos,
+    pub prev_pos: PrevPos,
+    pub rot: Rot,
+    pub prev_rot: PrevRot,
+    pub collider: BoxCollider,
+    pub vel: Velocity,
+    pub ang_vel: AngularVelocity,
+    pub restitution: Restitution,
+    pub friction: Friction,
+}
+
+impl KinematicBoxBundle {
+    pub fn new_with_pos_and_vel(pos: Vec2, vel: Vec2) -> Self {
+        Self {
+            rigid_body: RigidBody::Kinematic,
+            pos: Pos(pos),
+            prev_pos: PrevPos(pos),
+            rot: Rot::default(),
+            prev_rot: PrevRot::default(),
+            collider: BoxCollider::default(),
+            vel: Velocity(vel),
+            ang_vel: AngularVelocity::default(),
+            restitution: Restitution::default(),
+            friction: Friction::default(),
+        }
+    }
+}
+
+impl Default for KinematicBoxBundle {
+    fn default() -> Self {
+        Self::new_with_pos_and_vel(Vec2::ZERO, Vec2::ZERO)
+    }
+}
+
+/// Bodies spawned by [`spawn_rope`], from the start of the rope to its end.
+#[derive(Debug, Clone)]
+pub struct Rope {
+    pub links: Vec<Entity>,
+    /// Distance joints between consecutive links.
+    pub joints: Vec<Entity>,
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+impl Rope {
+    /// Holds the first link in place with a static point, which is returned.
+    pub fn pin_start(&self, commands: &mut Commands) -> Entity {
+        pin(commands, self.links[0], self.start)
+    }
+
+    /// Holds the last link in place with a static point, which is returned.
+    pub fn pin_end(&self, commands: &mut Commands) -> Entity {
+        pin(commands, self.links[self.links.len() - 1], self.end)
+    }
+
+    /// Lets the links collide with each other, neighbours are spaced so that they just touch.
+    pub fn enable_self_collision(&self, commands: &mut Commands) {
+        for link in self.links.iter() {
+            commands.entity(*link).remove::<CollisionGroup>();
+        }
+    }
+}
+
+fn pin(commands: &mut Commands, link: Entity, pos: Vec2) -> Entity {
+    let point = commands.spawn((RigidBody::Static, Pos(pos), Rot::default())).id();
+    commands.spawn(RevoluteJoint::new(point, link));
+    point
+}
+
+/// Spawns `segments + 1` particles evenly spaced from `start` to `end`, each one held to the next
+/// by a [`DistanceJoint`] with the given compliance. The links don't collide with each other.
+pub fn spawn_rope(
+    commands: &mut Commands,
+    start: Vec2,
+    end: Vec2,
+    segments: usize,
+    particle_mass: f32,
+    compliance: f32,
+) -> Rope {
+    let segments = segments.max(1);
+    let segment = (end - start) / segments as f32;
+    let links: Vec<Entity> = (0..=segments)
+        .map(|i| {
+            let pos = start + segment * i as f32;
+            commands
+                .spawn(ParticleBundle::new_with_pos_and_vel_and_mass_and_collider(
+                    pos,
+                    Vec2::ZERO,
+                    particle_mass,
+                    segment.length() / 2.,
+                ))
+                .id()
+        })
+        .collect();
+    for link in links.iter() {
+        commands.entity(*link).insert(CollisionGroup(links[0]));
+    }
+    let joints = links
+        .windows(2)
+        .map(|pair| {
+            commands
+                .spawn(
+                    DistanceJoint::new(pair[0], pair[1], segment.length())
+                        .with_compliance(compliance),
+                )
+                .id()
+        })
+        .collect();
+    Rope {
+        links,
+        joints,
+        start,
+        end,
+    }
+}
+
+/// Layout and stiffness of a cloth spawned by [`spawn_cloth`].
+#[derive(Debug, Clone)]
+pub struct ClothSettings {
+    /// Position of the top left particle.
+    pub origin: Vec2,
+    pub columns: usize,
+    pub rows: usize,
+    /// Distance between neighbouring particles.
+    pub spacing: f32,
+    pub particle_mass: f32,
+    /// Compliance of the joints between horizontal and vertical neighbours.
+    pub structural_compliance: f32,
+    /// Compliance of the joints between diagonal neighbours.
+    pub shear_compliance: f32,
+    /// Compliance of the joints that skip a particle, they only push apart to resist folding.
+    pub bending_compliance: f32,
+    /// Tears structural and shear joints stretched past this many times their rest length.
+    pub max_stretch: Option<f32>,
+}
+
+impl Default for ClothSettings {
+    fn default() -> Self {
+        Self {
+            origin: Vec2::ZERO,
+            columns: 10,
+            rows: 10,
+            spacing: 0.2,
+            particle_mass: 0.05,
+            structural_compliance: 0.,
+            shear_compliance: 0.0001,
+            bending_compliance: 0.01,
+            max_stretch: None,
+        }
+    }
+}
+
+/// Particles spawned by [`spawn_cloth`], row by row from the top left.
+#[derive(Debug, Clone)]
+pub struct Cloth {
+    pub particles: Vec<Entity>,
+    pub joints: Vec<Entity>,
+    pub columns: usize,
+    pub rows: usize,
+    origin: Vec2,
+    spacing: f32,
+}
+
+impl Cloth {
+    pub fn particle(&self, column: usize, row: usize) -> Entity {
+        self.particles[row * self.columns + column]
+    }
+
+    /// Holds a particle at the position it was spawned at with a static point, which is
+    /// returned.
+    pub fn pin(&self, commands: &mut Commands, column: usize, row: usize) -> Entity {
+        let pos = cloth_position(self.origin, self.spacing, column, row);
+        pin(commands, self.particle(column, row), pos)
+    }
+}
+
+fn cloth_position(origin: Vec2, spacing: f32, column: usize, row: usize) -> Vec2 {
+    origin + Vec2::new(column as f32, -(row as f32)) * spacing
+}
+
+/// Spawns a grid of particles with structural, shear and bending distance joints between them.
+/// The particles collide with other bodies but not with each other.
+pub fn spawn_cloth(commands: &mut Commands, settings: &ClothSettings) -> Cloth {
+    let ClothSettings {
+        origin,
+        columns,
+        rows,
+        spacing,
+        ..
+    } = *settings;
+    let mut particles = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            let particle = ParticleBundle::new_with_pos_and_vel_and_mass_and_collider(
+                cloth_position(origin, spacing, column, row),
+                Vec2::ZERO,
+                settings.particle_mass,
+                spacing / 2.,
+            );
+            particles.push(commands.spawn(particle).id());
+        }
+    }
+    for particle in particles.iter() {
+        commands
+            .entity(*particle)
+            .insert(CollisionGroup(particles[0]));
+    }
+
+    // Offsets to the neighbours each particle is joined to, with the rest length and compliance
+    // of the joint and whether it is a bending joint
+    let diagonal = spacing * std::f32::consts::SQRT_2;
+    let links = [
+        ((1, 0), spacing, settings.structural_compliance, false),
+        ((0, 1), spacing, settings.structural_compliance, false),
+        ((1, 1), diagonal, settings.shear_compliance, false),
+        ((-1, 1), diagonal, settings.shear_compliance, false),
+        ((2, 0), 2. * spacing, settings.bending_compliance, true),
+        ((0, 2), 2. * spacing, settings.bending_compliance, true),
+    ];
+    let mut joints = Vec::new();
+    for row in 0..rows {
+        for column in 0..columns {
+            for ((column_offset, row_offset), rest_length, compliance, bending) in links {
+                let other_column = column as isize + column_offset;
+                let other_row = row + row_offset;
+                if other_column < 0 || other_column >= columns as isize || other_row >= rows {
+                    continue;
+                }
+                let mut joint = DistanceJoint::new(
+                    particles[row * columns + column],
+                    particles[other_row * columns + other_column as usize],
+                    rest_length,
+                )
+                .with_compliance(compliance);
+                if bending {
+                    joint = joint.with_length_limits(rest_length, f32::INFINITY);
+                }
+                let mut entity = commands.spawn(joint);
+                if let (false, Some(max_stretch)) = (bending, settings.max_stretch) {
+                    entity.insert(MaxStretch(max_stretch));
+                }
+                joints.push(entity.id());
+            }
+        }
+    }
+
+    Cloth {
+        particles,
+        joints,
+        columns,
+        rows,
+        origin,
+        spacing,
+    }
+}
+
+/// Bodies spawned by [`spawn_pressure_blob`] or [`spawn_shape_matched_blob`].
+#[derive(Debug, Clone)]
+pub struct SoftBody {
+    /// Particles around the ring in counter-clockwise order.
+    pub particles: Vec<Entity>,
+    /// Distance joints between neighbouring particles, if the soft body has any.
+    pub joints: Vec<Entity>,
+    /// Entity holding the [`AreaConstraint`] or [`ShapeMatching`] of the soft body.
+    pub constraint: Entity,
+}
+
+/// Spawns a ring of particles that collide with other bodies but not with each other.
+fn spawn_ring(
+    commands: &mut Commands,
+    center: Vec2,
+    radius: f32,
+    segments: usize,
+    particle_mass: f32,
+) -> (Vec<Entity>, Vec<Vec2>) {
+    let segments = segments.max(3);
+    let positions: Vec<Vec2> = (0..segments)
+        .map(|i| {
+            let angle = std::f32::consts::TAU * i as f32 / segments as f32;
+            center + Vec2::from_angle(angle) * radius
+        })
+        .collect();
+    let particle_radius = positions[0].distance(positions[1]) / 2.;
+    let particles: Vec<Entity> = positions
+        .iter()
+        .map(|pos| {
+            commands
+                .spawn(ParticleBundle::new_with_pos_and_vel_and_mass_and_collider(
+                    *pos,
+                    Vec2::ZERO,
+                    particle_mass,
+                    particle_radius,
+                ))
+                .id()
+        })
+        .collect();
+    for particle in particles.iter() {
+        commands
+            .entity(*particle)
+            .insert(CollisionGroup(particles[0]));
+    }
+    (particles, positions)
+}
+
+/// Spawns a ring of particles held together by rigid distance joints, with an
+/// [`AreaConstraint`] that inflates it to `pressure` times its rest area.
+pub fn spawn_pressure_blob(
+    commands: &mut Commands,
+    center: Vec2,
+    radius: f32,
+    segments: usize,
+    particle_mass: f32,
+    pressure: f32,
+    compliance: f32,
+) -> SoftBody {
+    let (particles, positions) = spawn_ring(commands, center, radius, segments, particle_mass);
+    let n = particles.len();
+    let joints = (0..n)
+        .map(|i| {
+            let rest_length = positions[i].distance(positions[(i + 1) % n]);
+            commands
+                .spawn(DistanceJoint::new(
+                    particles[i],
+                    particles[(i + 1) % n],
+                    rest_length,
+                ))
+                .id()
+        })
+        .collect();
+    let constraint = commands
+        .spawn(AreaConstraint {
+            particles: particles.clone(),
+            rest_area: polygon_area(&positions),
+            pressure,
+            compliance,
+        })
+        .id();
+    SoftBody {
+        particles,
+        joints,
+        constraint,
+    }
+}
+
+/// Spawns a ring of particles that [`ShapeMatching`] pulls back into a circle.
+pub fn spawn_shape_matched_blob(
+    commands: &mut Commands,
+    center: Vec2,
+    radius: f32,
+    segments: usize,
+    particle_mass: f32,
+    stiffness: f32,
+) -> SoftBody {
+    let (particles, positions) = spawn_ring(commands, center, radius, segments, particle_mass);
+    let constraint = commands
+        .spawn(ShapeMatching::new(particles.clone(), &positions, stiffness))
+        .id();
+    SoftBody {
+        particles,
+        joints: Vec::new(),
+        constraint,
+    }
+}
+
+/// Particle of liquid, kept at the rest density of the [`FluidSettings`] by the fluid solver
+/// instead of bouncing off its neighbours.
+#[derive(Bundle)]
+pub struct FluidParticleBundle {
+    pub particle: ParticleBundle,
+    pub fluid: FluidParticle,
+}
+
+impl FluidParticleBundle {
+    pub fn new(pos: Vec2, vel: Vec2, mass: f32, collider_radius: f32) -> Self {
+        let mut particle = ParticleBundle::new_with_pos_and_vel_and_mass_and_collider(
+            pos,
+            vel,
+            mass,
+            collider_radius,
+        );
+        particle.restitution = Restitution(0.);
+        particle.friction = Friction {
+            static_coefficient: 0.,
+            dynamic_coefficient: 0.,
+        };
+        Self {
+            particle,
+            fluid: FluidParticle,
+        }
+    }
+}
+
+/// Spawns a block of `columns` by `rows` fluid particles with its bottom left one at `origin`,
+/// spaced and weighted so the block starts out at the rest density of `settings`.
+pub fn spawn_fluid_block(
+    commands: &mut Commands,
+    settings: &FluidSettings,
+    origin: Vec2,
+    columns: usize,
+    rows: usize,
+) -> Vec<Entity> {
+    let h = settings.kernel_radius;
+    let spacing = h / 2.;
+    // Unit mass particles on a grid of half the kernel radius have a density of 4.06 / h^2
+    let mass = settings.rest_density * h * h / 4.06;
+    let mut particles = Vec::with_capacity(columns * rows);
+    for r in 0..rows {
+        for c in 0..columns {
+            let pos = origin + Vec2::new(c as f32, r as f32) * spacing;
+            // The fluid solver keeps the particles off other bodies, the small collider only
+            // catches the ones it lets through
+            let particle = FluidParticleBundle::new(pos, Vec2::ZERO, mass, spacing / 4.);
+            particles.push(commands.spawn(particle).id());
+        }
+    }
+    particles
 }
@@ -0,0 +1,626 @@
+use std::f32::consts::PI;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::broadphase::{Aabb, Broadphase, SpatialHash};
+use crate::components::*;
+use crate::constraints::PoseQuery;
+use crate::narrowphase::{polygon_area, ColliderQuery, Shape};
+use crate::resources::{DeltaTime, SubDeltaTime, SubstepCount};
+
+/// Tuning of the position based fluid solver, shared by every [`FluidParticle`].
+#[derive(Debug, Resource)]
+pub struct FluidSettings {
+    /// Radius of the SPH kernels, particles further apart than this don't interact.
+    pub kernel_radius: f32,
+    /// Density the solver holds the fluid at, particles of mass 1 spaced 0.2 apart in a grid
+    /// have a density of about 25 with the default kernel radius. Bodies with less mass per
+    /// area than this float.
+    pub rest_density: f32,
+    /// Added to the denominator of the density constraint, keeps it from blowing up when a
+    /// particle has few neighbours.
+    pub relaxation: f32,
+    /// Strength of the artificial pressure that pushes apart particles closer than
+    /// `artificial_pressure_distance` kernel radii, which keeps them from clumping.
+    pub artificial_pressure: f32,
+    pub artificial_pressure_distance: f32,
+    pub artificial_pressure_exponent: i32,
+    /// How much of the average velocity of its neighbours a particle takes on each step, between
+    /// zero and one. It is spread over the substeps so the fluid is as thick for any
+    /// [`SubstepCount`].
+    pub viscosity: f32,
+    /// Strength of the vorticity confinement, which puts back the swirls lost to damping.
+    pub vorticity: f32,
+}
+
+impl Default for FluidSettings {
+    fn default() -> Self {
+        Self {
+            kernel_radius: 0.4,
+            rest_density: 25.,
+            relaxation: 100.,
+            artificial_pressure: 0.001,
+            artificial_pressure_distance: 0.2,
+            artificial_pressure_exponent: 4,
+            viscosity: 0.01,
+            vorticity: 0.05,
+        }
+    }
+}
+
+/// Fluid particles that might be within the kernel radius of each other or of another body,
+/// found once per step.
+#[derive(Default, Resource)]
+pub struct FluidNeighbors {
+    spatial_hash: SpatialHash,
+    proxies: Vec<(Entity, Aabb)>,
+    found: Vec<(Entity, Entity)>,
+    pub pairs: Vec<(Entity, Entity)>,
+    /// Fluid particles and the bodies they might be pressing on.
+    pub boundary_pairs: Vec<(Entity, Entity)>,
+}
+
+fn poly6(r_squared: f32, h: f32) -> f32 {
+    let h_squared = h * h;
+    if r_squared >= h_squared {
+        return 0.;
+    }
+    4. / (PI * h.powi(8)) * (h_squared - r_squared).powi(3)
+}
+
+fn spiky_gradient(r: Vec2, h: f32) -> Vec2 {
+    let length = r.length();
+    if length >= h || length <= f32::EPSILON {
+        return Vec2::ZERO;
+    }
+    r * (-30. / (PI * h.powi(5)) * (h - length).powi(2) / length)
+}
+
+/// Points along the outline of a body, each standing in for the fluid that would fill the
+/// stretch of wall around it, together with the length of that stretch.
+///
+/// The points sit half a particle spacing inside the surface, where the centers of a row of
+/// particles lining the wall from the other side would be. Only the points within `radius` of
+/// `center` are pushed, as offsets from the center of the body.
+fn boundary_points(
+    shape: Shape<'_>,
+    pos: Vec2,
+    rot: f32,
+    spacing: f32,
+    center: Vec2,
+    radius: f32,
+    points: &mut Vec<(Vec2, f32)>,
+) {
+    let inset = spacing / 2.;
+    let rotation = Vec2::from_angle(rot);
+    match shape {
+        Shape::Circle {
+            radius: circle_radius,
+        } => {
+            let inner_radius = (circle_radius - inset).max(0.);
+            arc_points(
+                pos,
+                Vec2::ZERO,
+                inner_radius,
+                rot,
+                2. * PI,
+                spacing,
+                center,
+                radius,
+                points,
+            );
+        }
+        Shape::Box { half_extents } => {
+            let inner = (half_extents - inset).max(Vec2::ZERO);
+            let corners = [
+                Vec2::new(-inner.x, -inner.y),
+                Vec2::new(inner.x, -inner.y),
+                Vec2::new(inner.x, inner.y),
+                Vec2::new(-inner.x, inner.y),
+            ]
+            .map(|corner| rotation.rotate(corner));
+            for i in 0..4 {
+                let (start, end) = (corners[i], corners[(i + 1) % 4]);
+                edge_points(pos, start, end, spacing, center, radius, points);
+            }
+        }
+        Shape::Capsule {
+            half_height,
+            radius: capsule_radius,
+        } => {
+            let inner_radius = (capsule_radius - inset).max(0.);
+            let top = rotation.rotate(Vec2::Y * half_height);
+            let side = rotation.rotate(Vec2::X * inner_radius);
+            // The sides and caps join end to end, each one leaves out the point the next starts at
+            edge_points(pos, side - top, side + top, spacing, center, radius, points);
+            edge_points(
+                pos,
+                top - side,
+                -top - side,
+                spacing,
+                center,
+                radius,
+                points,
+            );
+            for (cap, start) in [(top, rot), (-top, rot + PI)] {
+                arc_points(
+                    pos,
+                    cap,
+                    inner_radius,
+                    start,
+                    PI,
+                    spacing,
+                    center,
+                    radius,
+                    points,
+                );
+            }
+        }
+        Shape::Polygon { vertices } => {
+            let n = vertices.len();
+            let normal = |i: usize| {
+                let edge = vertices[(i + 1) % n] - vertices[i];
+                Vec2::new(edge.y, -edge.x).normalize_or_zero()
+            };
+            // Each corner moves in along the bisector until both of its edges are `inset` in
+            let inner: Vec<Vec2> = (0..n)
+                .map(|i| {
+                    let (before, after) = (normal((i + n - 1) % n), normal(i));
+                    let offset = (before + after) / (1. + before.dot(after)).max(f32::EPSILON);
+                    rotation.rotate(vertices[i] - offset * inset)
+                })
+                .collect();
+            // Bodies thinner than a particle have no inside left
+            if polygon_area(&inner) <= 0. {
+                return;
+            }
+            for i in 0..n {
+                let (start, end) = (inner[i], inner[(i + 1) % n]);
+                edge_points(pos, start, end, spacing, center, radius, points);
+            }
+        }
+        Shape::Polyline { vertices, closed } => {
+            let n = vertices.len();
+            let segments = if closed { n } else { n - 1 };
+            for i in 0..segments {
+                let (start, end) = (
+                    rotation.rotate(vertices[i]),
+                    rotation.rotate(vertices[(i + 1) % n]),
+                );
+                // The solid side of a segment is on its left
+                let inward = (end - start).perp().normalize_or_zero() * inset;
+                edge_points(
+                    pos,
+                    start + inward,
+                    end + inward,
+                    spacing,
+                    center,
+                    radius,
+                    points,
+                );
+            }
+        }
+    }
+}
+
+/// Evenly spaced points from `start` towards `end`, without `end` itself where the next edge
+/// starts.
+fn edge_points(
+    pos: Vec2,
+    start: Vec2,
+    end: Vec2,
+    spacing: f32,
+    center: Vec2,
+    radius: f32,
+    points: &mut Vec<(Vec2, f32)>,
+) {
+    let edge_length = start.distance(end);
+    if edge_length <= f32::EPSILON {
+        return;
+    }
+    let n = (edge_length / spacing).ceil();
+    let step = (end - start) / n;
+    // Only walk the part of the edge that can be in range
+    let t = (center - pos - start).dot(step) / step.length_squared();
+    let reach = radius / step.length();
+    let first = (t - reach).floor().max(0.) as usize;
+    let last = ((t + reach).ceil().min(n - 1.)).max(0.) as usize;
+    for k in first..=last {
+        let r = start + step * k as f32;
+        if (pos + r).distance_squared(center) < radius * radius {
+            points.push((r, edge_length / n));
+        }
+    }
+}
+
+/// Evenly spaced points on the arc around `arc_center` that starts at the angle `start` and
+/// sweeps counter-clockwise by `sweep`, without its end.
+#[allow(clippy::too_many_arguments)]
+fn arc_points(
+    pos: Vec2,
+    arc_center: Vec2,
+    arc_radius: f32,
+    start: f32,
+    sweep: f32,
+    spacing: f32,
+    center: Vec2,
+    radius: f32,
+    points: &mut Vec<(Vec2, f32)>,
+) {
+    let n = (sweep * arc_radius / spacing).ceil().max(1.);
+    let length = sweep * arc_radius / n;
+    for k in 0..n as usize {
+        let r = arc_center + Vec2::from_angle(start + k as f32 * sweep / n) * arc_radius;
+        if (pos + r).distance_squared(center) < radius * radius {
+            points.push((r, length.max(spacing)));
+        }
+    }
+}
+
+/// Pairs of particles within the kernel radius of each other, by their index in `positions`.
+fn close_pairs<'a>(
+    neighbors: &'a FluidNeighbors,
+    indices: &'a HashMap<Entity, usize>,
+    positions: &'a [Vec2],
+    h: f32,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    neighbors
+        .pairs
+        .iter()
+        .filter_map(move |(entity_a, entity_b)| {
+            let (i, j) = (*indices.get(entity_a)?, *indices.get(entity_b)?);
+            (positions[i].distance_squared(positions[j]) < h * h).then_some((i, j))
+        })
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn find_fluid_neighbors(
+    particles: Query<(Entity, &Pos, &Velocity), With<FluidParticle>>,
+    bodies: Query<(Entity, &Pos, &Rot, Option<&Velocity>, ColliderQuery), Without<FluidParticle>>,
+    settings: Res<FluidSettings>,
+    dt: Res<DeltaTime>,
+    mut neighbors: ResMut<FluidNeighbors>,
+) {
+    let neighbors = neighbors.as_mut();
+    let h = settings.kernel_radius;
+    neighbors.proxies.clear();
+    for (entity, pos, vel) in particles.iter() {
+        let aabb = Aabb::from_center_half_extents(pos.0, Vec2::splat(h / 2.));
+        let safety_margin = 2. * dt.0 * vel.0.length();
+        neighbors.proxies.push((entity, aabb.grow(safety_margin)));
+    }
+    for (entity, pos, rot, vel, collider) in bodies.iter() {
+        let Some(shape) = collider.shape() else {
+            continue;
+        };
+        let safety_margin = vel.map_or(0., |vel| 2. * dt.0 * vel.0.length());
+        let aabb = shape.aabb(pos.0, rot.0).grow(h / 2. + safety_margin);
+        neighbors.proxies.push((entity, aabb));
+    }
+    neighbors.spatial_hash.cell_size = h;
+    neighbors.spatial_hash.build(&neighbors.proxies);
+    neighbors.found.clear();
+    neighbors.spatial_hash.query_pairs(&mut neighbors.found);
+
+    neighbors.pairs.clear();
+    neighbors.boundary_pairs.clear();
+    for (entity_a, entity_b) in neighbors.found.iter().copied() {
+        match (particles.contains(entity_a), particles.contains(entity_b)) {
+            (true, true) => neighbors.pairs.push((entity_a, entity_b)),
+            (true, false) => neighbors.boundary_pairs.push((entity_a, entity_b)),
+            (false, true) => neighbors.boundary_pairs.push((entity_b, entity_a)),
+            (false, false) => {}
+        }
+    }
+}
+
+/// What the density solver knows about each particle during a substep.
+#[derive(Default, Clone, Copy)]
+struct DensityState {
+    pos: Vec2,
+    mass: f32,
+    density: f32,
+    /// Gradient of the particle's constraint with respect to its own position.
+    gradient: Vec2,
+    /// Sum of the squared gradients with respect to the positions of its neighbours and of the
+    /// bodies it touches, weighted by their inverse masses.
+    neighbor_gradients: f32,
+    lagrange: f32,
+    delta: Vec2,
+}
+
+/// A body close enough to a fluid particle to add to its density.
+#[derive(Clone, Copy)]
+struct BoundarySample {
+    particle: usize,
+    body: Entity,
+    /// Gradient of the particle's constraint with respect to its own position, the body gets
+    /// the opposite one.
+    gradient: Vec2,
+    /// Gradient of the particle's constraint with respect to the rotation of the body.
+    angular_gradient: f32,
+}
+
+/// Buffers reused by [`solve_fluid_density`] from one substep to the next.
+#[derive(Default)]
+pub(crate) struct DensityBuffers {
+    indices: HashMap<Entity, usize>,
+    positions: Vec<Vec2>,
+    states: Vec<DensityState>,
+    pairs: Vec<(usize, usize)>,
+    samples: Vec<BoundarySample>,
+    samples_per_body: HashMap<Entity, u32>,
+    points: Vec<(Vec2, f32)>,
+}
+
+/// Moves the particles to bring each of them back to the rest density, pushing the bodies they
+/// touch out of the way.
+///
+/// Bodies count towards the density of the particles next to them as if a row of particles
+/// lined their outline, so the fluid presses on them like on more fluid. Each correction is
+/// split between the particle and the body by their inverse masses, which makes bodies lighter
+/// than the fluid float.
+pub(crate) fn solve_fluid_density(
+    mut particles: Query<(Entity, &mut Pos, &Mass), With<FluidParticle>>,
+    mut bodies: Query<(PoseQuery, ColliderQuery), Without<FluidParticle>>,
+    settings: Res<FluidSettings>,
+    neighbors: Res<FluidNeighbors>,
+    mut buffers: Local<DensityBuffers>,
+) {
+    let DensityBuffers {
+        indices,
+        positions,
+        states,
+        pairs,
+        samples,
+        samples_per_body,
+        points,
+    } = &mut *buffers;
+    let h = settings.kernel_radius;
+    let rest_density = settings.rest_density;
+    indices.clear();
+    positions.clear();
+    states.clear();
+    for (i, (entity, pos, mass)) in particles.iter().enumerate() {
+        indices.insert(entity, i);
+        positions.push(pos.0);
+        states.push(DensityState {
+            pos: pos.0,
+            mass: mass.0,
+            density: mass.0 * poly6(0., h),
+            ..default()
+        });
+    }
+    pairs.clear();
+    pairs.extend(close_pairs(&neighbors, indices, positions, h));
+
+    for (i, j) in pairs.iter().copied() {
+        let (a, b) = (states[i], states[j]);
+        let r = a.pos - b.pos;
+        let w = poly6(r.length_squared(), h);
+        let gradient = spiky_gradient(r, h) / rest_density;
+        states[i].density += b.mass * w;
+        states[j].density += a.mass * w;
+        states[i].gradient += b.mass * gradient;
+        states[j].gradient -= a.mass * gradient;
+        // Each neighbour is moved in proportion to its own inverse mass
+        states[i].neighbor_gradients += (b.mass * gradient).length_squared() / b.mass;
+        states[j].neighbor_gradients += (a.mass * gradient).length_squared() / a.mass;
+    }
+
+    samples.clear();
+    samples_per_body.clear();
+    let spacing = h / 2.;
+    for (particle, body) in neighbors.boundary_pairs.iter().copied() {
+        let (Some(&i), Ok((mut pose, collider))) = (indices.get(&particle), bodies.get_mut(body))
+        else {
+            continue;
+        };
+        let Some(shape) = collider.shape() else {
+            continue;
+        };
+        let pose = pose.pose_body();
+        let body_pos = *pose.pos;
+        points.clear();
+        boundary_points(
+            shape,
+            body_pos,
+            *pose.rot,
+            spacing,
+            states[i].pos,
+            h,
+            points,
+        );
+        if points.is_empty() {
+            continue;
+        }
+        let mut sample = BoundarySample {
+            particle: i,
+            body,
+            gradient: Vec2::ZERO,
+            angular_gradient: 0.,
+        };
+        for (r, length) in points.iter().copied() {
+            let mass = rest_density * spacing * length;
+            let offset = states[i].pos - (body_pos + r);
+            let gradient = mass * spiky_gradient(offset, h) / rest_density;
+            states[i].density += mass * poly6(offset.length_squared(), h);
+            sample.gradient += gradient;
+            sample.angular_gradient -= r.perp_dot(gradient);
+        }
+        states[i].gradient += sample.gradient;
+        samples.push(sample);
+        *samples_per_body.entry(body).or_default() += 1;
+    }
+    // Every particle touching a body pushes it at the same time, so each of them sees it as
+    // that many times lighter. Otherwise they would all move it the whole way on their own.
+    for sample in samples.iter() {
+        let Ok((mut pose, _)) = bodies.get_mut(sample.body) else {
+            continue;
+        };
+        let pose = pose.pose_body();
+        let count = samples_per_body[&sample.body] as f32;
+        states[sample.particle].neighbor_gradients += count
+            * (pose.inverse_mass * sample.gradient.length_squared()
+                + pose.inverse_inertia * sample.angular_gradient.powi(2));
+    }
+
+    for state in states.iter_mut() {
+        // Only push particles apart, pulling them together clumps the particles at the surface
+        let c = (state.density / rest_density - 1.).max(0.);
+        let w_sum = state.gradient.length_squared() / state.mass + state.neighbor_gradients;
+        state.lagrange = -c / (w_sum + settings.relaxation);
+    }
+
+    let artificial_pressure_w = poly6((settings.artificial_pressure_distance * h).powi(2), h);
+    for (i, j) in pairs.iter().copied() {
+        let (a, b) = (states[i], states[j]);
+        let r = a.pos - b.pos;
+        let s_corr = -settings.artificial_pressure
+            * (poly6(r.length_squared(), h) / artificial_pressure_w)
+                .powi(settings.artificial_pressure_exponent);
+        let p = spiky_gradient(r, h) / rest_density
+            * (a.lagrange * b.mass + b.lagrange * a.mass + s_corr * (a.mass + b.mass) / 2.);
+        states[i].delta += p / a.mass;
+        states[j].delta -= p / b.mass;
+    }
+
+    for sample in samples.iter() {
+        let state = &mut states[sample.particle];
+        state.delta += state.lagrange * sample.gradient / state.mass;
+        let Ok((mut pose, _)) = bodies.get_mut(sample.body) else {
+            continue;
+        };
+        let pose = pose.pose_body();
+        *pose.pos -= state.lagrange * sample.gradient * pose.inverse_mass;
+        *pose.rot += state.lagrange * sample.angular_gradient * pose.inverse_inertia;
+    }
+
+    for (state, (_, mut pos, _)) in states.iter().zip(particles.iter_mut()) {
+        pos.0 += state.delta;
+    }
+}
+
+/// Buffers reused by [`apply_fluid_viscosity`] from one substep to the next.
+#[derive(Default)]
+pub(crate) struct ViscosityBuffers {
+    indices: HashMap<Entity, usize>,
+    positions: Vec<Vec2>,
+    velocities: Vec<Vec2>,
+    pairs: Vec<(usize, usize)>,
+    viscosity: Vec<Vec2>,
+    vorticity: Vec<f32>,
+    vorticity_gradient: Vec<Vec2>,
+}
+
+/// Smooths the velocities with XSPH viscosity and adds back swirls with vorticity confinement.
+pub(crate) fn apply_fluid_viscosity(
+    mut particles: Query<(Entity, &Pos, &mut Velocity), With<FluidParticle>>,
+    settings: Res<FluidSettings>,
+    neighbors: Res<FluidNeighbors>,
+    sub_dt: Res<SubDeltaTime>,
+    substeps: Res<SubstepCount>,
+    mut buffers: Local<ViscosityBuffers>,
+) {
+    let ViscosityBuffers {
+        indices,
+        positions,
+        velocities,
+        pairs,
+        viscosity,
+        vorticity,
+        vorticity_gradient,
+    } = &mut *buffers;
+    let h = settings.kernel_radius;
+    indices.clear();
+    positions.clear();
+    velocities.clear();
+    for (i, (entity, pos, vel)) in particles.iter().enumerate() {
+        indices.insert(entity, i);
+        positions.push(pos.0);
+        velocities.push(vel.0);
+    }
+    pairs.clear();
+    pairs.extend(close_pairs(&neighbors, indices, positions, h));
+
+    let n = positions.len();
+    viscosity.clear();
+    viscosity.resize(n, Vec2::ZERO);
+    vorticity.clear();
+    vorticity.resize(n, 0.);
+    for (i, j) in pairs.iter().copied() {
+        let r = positions[i] - positions[j];
+        let relative_vel = velocities[j] - velocities[i];
+        let w = poly6(r.length_squared(), h) / settings.rest_density;
+        viscosity[i] += relative_vel * w;
+        viscosity[j] -= relative_vel * w;
+        // Curl of the velocity field, which is a scalar in 2D
+        let curl = spiky_gradient(r, h).perp_dot(relative_vel) / settings.rest_density;
+        vorticity[i] += curl;
+        vorticity[j] += curl;
+    }
+
+    // Push each particle sideways to its swirl, away from the direction the swirls get weaker
+    vorticity_gradient.clear();
+    vorticity_gradient.resize(n, Vec2::ZERO);
+    for (i, j) in pairs.iter().copied() {
+        let gradient = spiky_gradient(positions[i] - positions[j], h);
+        vorticity_gradient[i] += vorticity[j].abs() * gradient;
+        vorticity_gradient[j] -= vorticity[i].abs() * gradient;
+    }
+
+    // Smoothing by `k` each of `n` substeps leaves `(1 - k)^n` of the difference, which is what a
+    // single smoothing by `viscosity` leaves
+    let substeps = substeps.0.max(1) as f32;
+    let smoothing = 1. - (1. - settings.viscosity.clamp(0., 1.)).powf(1. / substeps);
+    for (i, (_, _, mut vel)) in particles.iter_mut().enumerate() {
+        let n = vorticity_gradient[i].normalize_or_zero();
+        let confinement = Vec2::new(n.y, -n.x) * vorticity[i];
+        vel.0 += smoothing * viscosity[i] + settings.vorticity * confinement * sub_dt.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+
+    #[test]
+    fn density_correction_with_mixed_masses() {
+        let settings = FluidSettings {
+            relaxation: 0.,
+            artificial_pressure: 0.,
+            ..default()
+        };
+        let h = settings.kernel_radius;
+        let (heavy_mass, light_mass) = (4., 1.);
+        let offset = Vec2::new(0.5 * h, 0.);
+        let density =
+            |r: Vec2| heavy_mass * poly6(0., h) + light_mass * poly6(r.length_squared(), h);
+        // Only the heavy particle is denser than the rest density, by 5%
+        let rest_density = density(offset) / 1.05;
+
+        let mut world = World::new();
+        world.insert_resource(FluidSettings {
+            rest_density,
+            ..settings
+        });
+        let heavy = world
+            .spawn((FluidParticle, Pos(Vec2::ZERO), Mass(heavy_mass)))
+            .id();
+        let light = world
+            .spawn((FluidParticle, Pos(offset), Mass(light_mass)))
+            .id();
+        world.insert_resource(FluidNeighbors {
+            pairs: vec![(heavy, light)],
+            ..default()
+        });
+        world.run_system_once(solve_fluid_density);
+
+        // A single constraint is solved in one go, up to the curvature of the kernel
+        let r = world.get::<Pos>(light).unwrap().0 - world.get::<Pos>(heavy).unwrap().0;
+        let c = density(r) / rest_density - 1.;
+        assert!(c.abs() < 0.01, "{c}");
+    }
+}
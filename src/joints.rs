@@ -0,0 +1,761 @@
+use bevy::prelude::*;
+
+use crate::components::Pos;
+use crate::constraints::{JointBroken, PositionConstraint};
+use crate::solver::{
+    apply_angular_constraint, apply_angular_correction, apply_positional_constraint,
+    apply_positional_correction, PoseBody,
+};
+
+/// Keeps the centers of two bodies `rest_length` apart.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DistanceJoint {
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+    pub rest_length: f32,
+    /// Lets the distance move freely between a minimum and a maximum instead of holding it at
+    /// `rest_length`, `(0., length)` behaves like a rope.
+    pub length_limits: Option<(f32, f32)>,
+    pub compliance: f32,
+    lagrange: f32,
+    force: Vec2,
+}
+
+impl DistanceJoint {
+    pub fn new(entity_a: Entity, entity_b: Entity, rest_length: f32) -> Self {
+        Self {
+            entity_a,
+            entity_b,
+            rest_length,
+            length_limits: None,
+            compliance: 0.,
+            lagrange: 0.,
+            force: Vec2::ZERO,
+        }
+    }
+
+    pub fn with_length_limits(self, min: f32, max: f32) -> Self {
+        Self {
+            length_limits: Some((min, max)),
+            ..self
+        }
+    }
+
+    pub fn with_compliance(self, compliance: f32) -> Self {
+        Self { compliance, ..self }
+    }
+}
+
+impl PositionConstraint for DistanceJoint {
+    fn entities(&self) -> [Entity; 2] {
+        [self.entity_a, self.entity_b]
+    }
+
+    fn clear_lagrange_multipliers(&mut self) {
+        self.lagrange = 0.;
+    }
+
+    fn force(&self) -> Vec2 {
+        self.force
+    }
+
+    fn torque(&self) -> f32 {
+        0.
+    }
+
+    fn solve(&mut self, body_a: &mut PoseBody, body_b: &mut PoseBody, dt: f32) {
+        self.force = Vec2::ZERO;
+        let delta = *body_a.pos - *body_b.pos;
+        let length = delta.length();
+        if length <= f32::EPSILON {
+            return;
+        }
+        let c = match self.length_limits {
+            Some((min, _)) if length < min => length - min,
+            Some((_, max)) if length > max => length - max,
+            Some(_) => return,
+            None => length - self.rest_length,
+        };
+        let n = delta / length;
+        apply_positional_constraint(
+            body_a,
+            body_b,
+            &mut self.lagrange,
+            c,
+            n,
+            Vec2::ZERO,
+            Vec2::ZERO,
+            self.compliance,
+            dt,
+        );
+        self.force = n * self.lagrange / (dt * dt);
+    }
+}
+
+/// Removes the distance joint on the same entity once it is stretched past this many times its
+/// rest length, sending a [`JointBroken`] event.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct MaxStretch(pub f32);
+
+pub(crate) fn tear_distance_joints(
+    joints: Query<(Entity, &DistanceJoint, &MaxStretch)>,
+    bodies: Query<&Pos>,
+    mut commands: Commands,
+    mut joint_broken: EventWriter<JointBroken>,
+) {
+    for (joint, distance_joint, max_stretch) in joints.iter() {
+        let Ok([pos_a, pos_b]) = bodies.get_many(distance_joint.entities()) else {
+            continue;
+        };
+        if pos_a.0.distance(pos_b.0) > max_stretch.0 * distance_joint.rest_length {
+            commands
+                .entity(joint)
+                .remove::<(DistanceJoint, MaxStretch)>();
+            joint_broken.send(JointBroken {
+                joint,
+                entity_a: distance_joint.entity_a,
+                entity_b: distance_joint.entity_b,
+                force: distance_joint.force,
+                torque: 0.,
+            });
+        }
+    }
+}
+
+/// Drives the relative rotation of a joint towards `target_velocity` radians per second without
+/// applying more than `max_torque`.
+#[derive(Debug, Clone, Copy)]
+pub struct AngularMotor {
+    pub target_velocity: f32,
+    pub max_torque: f32,
+}
+
+/// Pins a point on each body together while letting them rotate around it.
+///
+/// Angles are the rotation of the second body relative to the first one, counted from
+/// `reference_angle`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct RevoluteJoint {
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+    /// Attachment point in the local space of the first body.
+    pub local_anchor_a: Vec2,
+    /// Attachment point in the local space of the second body.
+    pub local_anchor_b: Vec2,
+    /// Relative rotation of the bodies at which the joint is at an angle of zero, usually the
+    /// one they were spawned with.
+    pub reference_angle: f32,
+    pub angle_limits: Option<(f32, f32)>,
+    pub motor: Option<AngularMotor>,
+    pub compliance: f32,
+    position_lagrange: f32,
+    angle_lagrange: f32,
+    motor_lagrange: f32,
+    force: Vec2,
+    torque: f32,
+}
+
+impl RevoluteJoint {
+    pub fn new(entity_a: Entity, entity_b: Entity) -> Self {
+        Self {
+            entity_a,
+            entity_b,
+            local_anchor_a: Vec2::ZERO,
+            local_anchor_b: Vec2::ZERO,
+            reference_angle: 0.,
+            angle_limits: None,
+            motor: None,
+            compliance: 0.,
+            position_lagrange: 0.,
+            angle_lagrange: 0.,
+            motor_lagrange: 0.,
+            force: Vec2::ZERO,
+            torque: 0.,
+        }
+    }
+
+    pub fn with_local_anchor_a(self, local_anchor_a: Vec2) -> Self {
+        Self {
+            local_anchor_a,
+            ..self
+        }
+    }
+
+    pub fn with_local_anchor_b(self, local_anchor_b: Vec2) -> Self {
+        Self {
+            local_anchor_b,
+            ..self
+        }
+    }
+
+    pub fn with_reference_angle(self, reference_angle: f32) -> Self {
+        Self {
+            reference_angle,
+            ..self
+        }
+    }
+
+    pub fn with_angle_limits(self, min: f32, max: f32) -> Self {
+        Self {
+            angle_limits: Some((min, max)),
+            ..self
+        }
+    }
+
+    pub fn with_motor(self, target_velocity: f32, max_torque: f32) -> Self {
+        Self {
+            motor: Some(AngularMotor {
+                target_velocity,
+                max_torque,
+            }),
+            ..self
+        }
+    }
+
+    pub fn with_compliance(self, compliance: f32) -> Self {
+        Self { compliance, ..self }
+    }
+}
+
+impl PositionConstraint for RevoluteJoint {
+    fn entities(&self) -> [Entity; 2] {
+        [self.entity_a, self.entity_b]
+    }
+
+    fn clear_lagrange_multipliers(&mut self) {
+        self.position_lagrange = 0.;
+        self.angle_lagrange = 0.;
+        self.motor_lagrange = 0.;
+    }
+
+    fn force(&self) -> Vec2 {
+        self.force
+    }
+
+    fn torque(&self) -> f32 {
+        self.torque
+    }
+
+    fn solve(&mut self, body_a: &mut PoseBody, body_b: &mut PoseBody, dt: f32) {
+        if let Some(motor) = self.motor {
+            self.motor_lagrange += solve_angular_motor(body_a, body_b, motor, dt);
+        }
+        if let Some((min, max)) = self.angle_limits {
+            solve_angle_limits(
+                body_a,
+                body_b,
+                &mut self.angle_lagrange,
+                self.reference_angle + min,
+                self.reference_angle + max,
+                self.compliance,
+                dt,
+            );
+        }
+        let n = solve_anchors(
+            body_a,
+            body_b,
+            &mut self.position_lagrange,
+            self.local_anchor_a,
+            self.local_anchor_b,
+            self.compliance,
+            dt,
+        );
+        self.force = n * self.position_lagrange / (dt * dt);
+        self.torque = (self.angle_lagrange + self.motor_lagrange) / (dt * dt);
+    }
+}
+
+/// Drives the relative translation of a joint towards `target_velocity` units per second
+/// without applying more than `max_force`.
+#[derive(Debug, Clone, Copy)]
+pub struct LinearMotor {
+    pub target_velocity: f32,
+    pub max_force: f32,
+}
+
+/// Lets the anchor of the second body slide along an axis fixed to the first body, while the
+/// rotation of the second body relative to the first one stays at `reference_angle`.
+///
+/// Translations are the distance of the second anchor from the first one along the axis.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PrismaticJoint {
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+    /// Attachment point in the local space of the first body.
+    pub local_anchor_a: Vec2,
+    /// Attachment point in the local space of the second body.
+    pub local_anchor_b: Vec2,
+    /// Direction of the slide in the local space of the first body.
+    pub local_axis: Vec2,
+    pub reference_angle: f32,
+    pub translation_limits: Option<(f32, f32)>,
+    pub motor: Option<LinearMotor>,
+    pub compliance: f32,
+    position_lagrange: f32,
+    angle_lagrange: f32,
+    limit_lagrange: f32,
+    motor_lagrange: f32,
+    force: Vec2,
+    torque: f32,
+}
+
+impl PrismaticJoint {
+    /// Panics when `local_axis` is zero, there is no direction to slide along.
+    pub fn new(entity_a: Entity, entity_b: Entity, local_axis: Vec2) -> Self {
+        Self {
+            entity_a,
+            entity_b,
+            local_anchor_a: Vec2::ZERO,
+            local_anchor_b: Vec2::ZERO,
+            local_axis: local_axis
+                .try_normalize()
+                .expect("the axis of a prismatic joint must not be zero"),
+            reference_angle: 0.,
+            translation_limits: None,
+            motor: None,
+            compliance: 0.,
+            position_lagrange: 0.,
+            angle_lagrange: 0.,
+            limit_lagrange: 0.,
+            motor_lagrange: 0.,
+            force: Vec2::ZERO,
+            torque: 0.,
+        }
+    }
+
+    pub fn with_local_anchor_a(self, local_anchor_a: Vec2) -> Self {
+        Self {
+            local_anchor_a,
+            ..self
+        }
+    }
+
+    pub fn with_local_anchor_b(self, local_anchor_b: Vec2) -> Self {
+        Self {
+            local_anchor_b,
+            ..self
+        }
+    }
+
+    pub fn with_reference_angle(self, reference_angle: f32) -> Self {
+        Self {
+            reference_angle,
+            ..self
+        }
+    }
+
+    pub fn with_translation_limits(self, min: f32, max: f32) -> Self {
+        Self {
+            translation_limits: Some((min, max)),
+            ..self
+        }
+    }
+
+    pub fn with_motor(self, target_velocity: f32, max_force: f32) -> Self {
+        Self {
+            motor: Some(LinearMotor {
+                target_velocity,
+                max_force,
+            }),
+            ..self
+        }
+    }
+
+    pub fn with_compliance(self, compliance: f32) -> Self {
+        Self { compliance, ..self }
+    }
+}
+
+impl PositionConstraint for PrismaticJoint {
+    fn entities(&self) -> [Entity; 2] {
+        [self.entity_a, self.entity_b]
+    }
+
+    fn clear_lagrange_multipliers(&mut self) {
+        self.position_lagrange = 0.;
+        self.angle_lagrange = 0.;
+        self.limit_lagrange = 0.;
+        self.motor_lagrange = 0.;
+    }
+
+    fn force(&self) -> Vec2 {
+        self.force
+    }
+
+    fn torque(&self) -> f32 {
+        self.torque
+    }
+
+    fn solve(&mut self, body_a: &mut PoseBody, body_b: &mut PoseBody, dt: f32) {
+        solve_angle_limits(
+            body_a,
+            body_b,
+            &mut self.angle_lagrange,
+            self.reference_angle,
+            self.reference_angle,
+            self.compliance,
+            dt,
+        );
+
+        let axis = body_a.to_world(self.local_axis);
+        if let Some(motor) = self.motor {
+            self.motor_lagrange += solve_linear_motor(
+                body_a,
+                body_b,
+                motor,
+                axis,
+                self.local_anchor_a,
+                self.local_anchor_b,
+                dt,
+            );
+        }
+        if let Some((min, max)) = self.translation_limits {
+            let r_a = body_a.to_world(self.local_anchor_a);
+            let r_b = body_b.to_world(self.local_anchor_b);
+            let translation = ((*body_b.pos + r_b) - (*body_a.pos + r_a)).dot(axis);
+            let limit = translation.clamp(min, max);
+            if translation != limit {
+                apply_positional_constraint(
+                    body_a,
+                    body_b,
+                    &mut self.limit_lagrange,
+                    translation - limit,
+                    -axis,
+                    r_a,
+                    r_b,
+                    self.compliance,
+                    dt,
+                );
+            }
+        }
+
+        // Remove whatever is left of the offset across the axis
+        let r_a = body_a.to_world(self.local_anchor_a);
+        let r_b = body_b.to_world(self.local_anchor_b);
+        let delta = (*body_a.pos + r_a) - (*body_b.pos + r_b);
+        let off_axis = delta - axis * delta.dot(axis);
+        let n = off_axis.normalize_or_zero();
+        if n != Vec2::ZERO {
+            apply_positional_constraint(
+                body_a,
+                body_b,
+                &mut self.position_lagrange,
+                off_axis.length(),
+                n,
+                r_a,
+                r_b,
+                self.compliance,
+                dt,
+            );
+        }
+
+        let along_axis = self.motor_lagrange - self.limit_lagrange;
+        self.force = (n * self.position_lagrange + axis * along_axis) / (dt * dt);
+        self.torque = self.angle_lagrange / (dt * dt);
+    }
+}
+
+/// Locks the anchors of the two bodies together and holds the rotation of the second body
+/// relative to the first one at `reference_angle`, a compliance above zero makes the weld wobbly.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FixedJoint {
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+    /// Attachment point in the local space of the first body.
+    pub local_anchor_a: Vec2,
+    /// Attachment point in the local space of the second body.
+    pub local_anchor_b: Vec2,
+    pub reference_angle: f32,
+    pub compliance: f32,
+    position_lagrange: f32,
+    angle_lagrange: f32,
+    force: Vec2,
+    torque: f32,
+}
+
+impl FixedJoint {
+    pub fn new(entity_a: Entity, entity_b: Entity) -> Self {
+        Self {
+            entity_a,
+            entity_b,
+            local_anchor_a: Vec2::ZERO,
+            local_anchor_b: Vec2::ZERO,
+            reference_angle: 0.,
+            compliance: 0.,
+            position_lagrange: 0.,
+            angle_lagrange: 0.,
+            force: Vec2::ZERO,
+            torque: 0.,
+        }
+    }
+
+    pub fn with_local_anchor_a(self, local_anchor_a: Vec2) -> Self {
+        Self {
+            local_anchor_a,
+            ..self
+        }
+    }
+
+    pub fn with_local_anchor_b(self, local_anchor_b: Vec2) -> Self {
+        Self {
+            local_anchor_b,
+            ..self
+        }
+    }
+
+    pub fn with_reference_angle(self, reference_angle: f32) -> Self {
+        Self {
+            reference_angle,
+            ..self
+        }
+    }
+
+    pub fn with_compliance(self, compliance: f32) -> Self {
+        Self { compliance, ..self }
+    }
+}
+
+impl PositionConstraint for FixedJoint {
+    fn entities(&self) -> [Entity; 2] {
+        [self.entity_a, self.entity_b]
+    }
+
+    fn clear_lagrange_multipliers(&mut self) {
+        self.position_lagrange = 0.;
+        self.angle_lagrange = 0.;
+    }
+
+    fn force(&self) -> Vec2 {
+        self.force
+    }
+
+    fn torque(&self) -> f32 {
+        self.torque
+    }
+
+    fn solve(&mut self, body_a: &mut PoseBody, body_b: &mut PoseBody, dt: f32) {
+        solve_angle_limits(
+            body_a,
+            body_b,
+            &mut self.angle_lagrange,
+            self.reference_angle,
+            self.reference_angle,
+            self.compliance,
+            dt,
+        );
+        let n = solve_anchors(
+            body_a,
+            body_b,
+            &mut self.position_lagrange,
+            self.local_anchor_a,
+            self.local_anchor_b,
+            self.compliance,
+            dt,
+        );
+        self.force = n * self.position_lagrange / (dt * dt);
+        self.torque = self.angle_lagrange / (dt * dt);
+    }
+}
+
+/// Pulls the anchors of the two bodies onto each other and returns the direction the first
+/// body was pulled in.
+fn solve_anchors(
+    body_a: &mut PoseBody,
+    body_b: &mut PoseBody,
+    lagrange: &mut f32,
+    local_anchor_a: Vec2,
+    local_anchor_b: Vec2,
+    compliance: f32,
+    dt: f32,
+) -> Vec2 {
+    let r_a = body_a.to_world(local_anchor_a);
+    let r_b = body_b.to_world(local_anchor_b);
+    let delta = (*body_a.pos + r_a) - (*body_b.pos + r_b);
+    let distance = delta.length();
+    if distance <= f32::EPSILON {
+        return Vec2::ZERO;
+    }
+    let n = delta / distance;
+    apply_positional_constraint(
+        body_a, body_b, lagrange, distance, n, r_a, r_b, compliance, dt,
+    );
+    n
+}
+
+/// Keeps the rotation of the second body relative to the first one between `min` and `max`.
+fn solve_angle_limits(
+    body_a: &mut PoseBody,
+    body_b: &mut PoseBody,
+    lagrange: &mut f32,
+    min: f32,
+    max: f32,
+    compliance: f32,
+    dt: f32,
+) {
+    let angle = *body_b.rot - *body_a.rot;
+    let limit = if angle < min {
+        min
+    } else if angle > max {
+        max
+    } else {
+        return;
+    };
+    apply_angular_constraint(body_a, body_b, lagrange, limit - angle, compliance, dt);
+}
+
+/// Corrects the relative rotation of this substep towards the target velocity of the motor and
+/// returns the correction, which is clamped to the torque limit.
+fn solve_angular_motor(
+    body_a: &mut PoseBody,
+    body_b: &mut PoseBody,
+    motor: AngularMotor,
+    dt: f32,
+) -> f32 {
+    let w_sum = body_a.inverse_inertia + body_b.inverse_inertia;
+    if w_sum <= f32::EPSILON {
+        return 0.;
+    }
+    let rotation = (*body_b.rot - body_b.prev_rot) - (*body_a.rot - body_a.prev_rot);
+    let c = motor.target_velocity * dt - rotation;
+    let max_lagrange = motor.max_torque * dt * dt;
+    let delta_lagrange = (-c / w_sum).clamp(-max_lagrange, max_lagrange);
+    apply_angular_correction(body_a, body_b, delta_lagrange);
+    delta_lagrange
+}
+
+/// Corrects the relative translation along `axis` of this substep towards the target velocity
+/// of the motor and returns the correction, which is clamped to the force limit.
+fn solve_linear_motor(
+    body_a: &mut PoseBody,
+    body_b: &mut PoseBody,
+    motor: LinearMotor,
+    axis: Vec2,
+    local_anchor_a: Vec2,
+    local_anchor_b: Vec2,
+    dt: f32,
+) -> f32 {
+    let r_a = body_a.to_world(local_anchor_a);
+    let r_b = body_b.to_world(local_anchor_b);
+    let w_sum =
+        body_a.generalized_inverse_mass(r_a, axis) + body_b.generalized_inverse_mass(r_b, axis);
+    if w_sum <= f32::EPSILON {
+        return 0.;
+    }
+    let translation = (body_b.displacement(r_b) - body_a.displacement(r_a)).dot(axis);
+    let c = motor.target_velocity * dt - translation;
+    let max_lagrange = motor.max_force * dt * dt;
+    let delta_lagrange = (-c / w_sum).clamp(-max_lagrange, max_lagrange);
+    apply_positional_correction(body_a, body_b, delta_lagrange, axis, r_a, r_b);
+    delta_lagrange
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::constraints::{break_constraints, BreakForce, BreakTorque};
+
+    /// Solves a joint between a static body at the origin and a body of unit mass at `pos_b`
+    /// for a few substeps, returns the pose the second body ends up in.
+    fn solve(joint: &mut impl PositionConstraint, pos_b: Vec2, rot_b: f32) -> (Vec2, f32) {
+        solve_substeps(joint, pos_b, rot_b, 10)
+    }
+
+    fn solve_substeps(
+        joint: &mut impl PositionConstraint,
+        pos_b: Vec2,
+        rot_b: f32,
+        substeps: usize,
+    ) -> (Vec2, f32) {
+        let (mut pos_a, mut rot_a) = (Vec2::ZERO, 0.);
+        let (mut pos_b, mut rot_b) = (pos_b, rot_b);
+        for _ in 0..substeps {
+            let (prev_pos, prev_rot) = (pos_b, rot_b);
+            joint.clear_lagrange_multipliers();
+            joint.solve(
+                &mut PoseBody {
+                    pos: &mut pos_a,
+                    rot: &mut rot_a,
+                    prev_pos: Vec2::ZERO,
+                    prev_rot: 0.,
+                    start_rot: 0.,
+                    inverse_mass: 0.,
+                    inverse_inertia: 0.,
+                },
+                &mut PoseBody {
+                    pos: &mut pos_b,
+                    rot: &mut rot_b,
+                    prev_pos,
+                    prev_rot,
+                    start_rot: prev_rot,
+                    inverse_mass: 1.,
+                    inverse_inertia: 1.,
+                },
+                1. / 60.,
+            );
+        }
+        (pos_b, rot_b)
+    }
+
+    #[test]
+    fn prismatic_joint_slides_within_its_limits() {
+        let mut joint = PrismaticJoint::new(Entity::PLACEHOLDER, Entity::PLACEHOLDER, Vec2::X * 3.)
+            .with_translation_limits(0., 1.);
+        assert_eq!(joint.local_axis, Vec2::X);
+        let (pos, rot) = solve(&mut joint, Vec2::new(2., 0.3), 0.2);
+        assert!(pos.abs_diff_eq(Vec2::new(1., 0.), 1e-3), "{pos:?}");
+        assert!(rot.abs() < 1e-3, "{rot}");
+        let (pos, _) = solve(&mut joint, Vec2::new(0.5, 0.), 0.);
+        assert!(pos.abs_diff_eq(Vec2::new(0.5, 0.), 1e-5), "{pos:?}");
+        let (_, rot) = solve(
+            &mut joint.with_reference_angle(FRAC_PI_2),
+            Vec2::new(0.5, 0.),
+            FRAC_PI_2 + 0.2,
+        );
+        assert!((rot - FRAC_PI_2).abs() < 1e-3, "{rot}");
+    }
+
+    #[test]
+    #[should_panic]
+    fn prismatic_joint_needs_an_axis() {
+        PrismaticJoint::new(Entity::PLACEHOLDER, Entity::PLACEHOLDER, Vec2::ZERO);
+    }
+
+    #[test]
+    fn revolute_joint_limits_count_from_the_reference_angle() {
+        let mut joint = RevoluteJoint::new(Entity::PLACEHOLDER, Entity::PLACEHOLDER)
+            .with_reference_angle(FRAC_PI_2)
+            .with_angle_limits(-0.1, 0.1);
+        let (pos, rot) = solve(&mut joint, Vec2::ZERO, FRAC_PI_2 + 0.5);
+        assert!(pos.abs_diff_eq(Vec2::ZERO, 1e-5), "{pos:?}");
+        assert!((rot - (FRAC_PI_2 + 0.1)).abs() < 1e-4, "{rot}");
+        let (_, rot) = solve(&mut joint, Vec2::ZERO, FRAC_PI_2 - 0.05);
+        assert!((rot - (FRAC_PI_2 - 0.05)).abs() < 1e-6, "{rot}");
+    }
+
+    #[test]
+    fn twisted_weld_breaks_past_its_break_torque() {
+        let mut joint = FixedJoint::new(Entity::PLACEHOLDER, Entity::PLACEHOLDER);
+        // Twisting the weld in place loads it in torque only
+        solve_substeps(&mut joint, Vec2::ZERO, 0.5, 1);
+        assert!(joint.force().length() < 1e-3);
+        assert!(joint.torque().abs() > 1000.);
+
+        let mut world = World::new();
+        world.init_resource::<Events<JointBroken>>();
+        let held = world.spawn((joint, BreakForce(1000.))).id();
+        let broken = world.spawn((joint, BreakTorque(1000.))).id();
+        world.run_system_once(break_constraints::<FixedJoint>);
+        assert!(world.get::<FixedJoint>(held).is_some());
+        assert!(world.get::<FixedJoint>(broken).is_none());
+
+        let events = world.resource::<Events<JointBroken>>();
+        let sent: Vec<_> = events.get_reader().read(events).copied().collect();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].joint, broken);
+        assert_eq!(sent[0].torque, joint.torque());
+    }
+}
@@ -1,43 +1,333 @@
-use bevy::prelude::*;
+use bevy::{
+    ecs::{query::QueryData, schedule::ScheduleLabel},
+    prelude::*,
+};
+pub mod broadphase;
 pub mod components;
+pub mod constraints;
 pub mod entity;
+pub mod fluid;
+pub mod joints;
+pub mod narrowphase;
 pub mod resources;
-pub const DELTA_TIME: f32 = 1. / 60.;
+pub mod softbody;
+pub mod solver;
 
+use broadphase::{collect_collision_pairs, Broadphase, DynamicAabbTree};
 use components::*;
-use resources::{CollisionPairs, Contacts, Gravity, StaticContacts};
+use constraints::{
+    break_constraints, clear_lagrange_multipliers, solve_constraints, JointBroken, PoseQuery,
+    PositionConstraint,
+};
+use fluid::{
+    apply_fluid_viscosity, find_fluid_neighbors, solve_fluid_density, FluidNeighbors, FluidSettings,
+};
+use joints::{tear_distance_joints, DistanceJoint, FixedJoint, PrismaticJoint, RevoluteJoint};
+use narrowphase::{collide, ColliderQuery};
+use resources::{
+    CollisionPairs, Contacts, DeltaTime, Gravity, PhysicsTimestep, SubDeltaTime, SubstepCount,
+};
+use softbody::{solve_area_constraints, solve_shape_matching};
+use solver::{solve_contact_vel, solve_manifold_pos, ContactConstraint, VelocityBody};
 
-#[derive(Debug, Default)]
-pub struct XPBDPlugin;
+/// Runs one physics step, from `FixedUpdate` or `Update` depending on the [`PhysicsTimestep`].
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhysicsSchedule;
+
+/// Stages of a physics step, in the order they run.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PhysicsSet {
+    Prepare,
+    Broadphase,
+    Substeps,
+    Sync,
+}
+
+/// Runs the substeps of a physics step, [`SubstepCount`] times per [`PhysicsSchedule`].
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SubstepSchedule;
+
+/// Stages of a substep, in the order they run.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SubstepSet {
+    Integrate,
+    SolvePos,
+    SolveConstraints,
+    UpdateVel,
+    SolveVel,
+}
+
+#[derive(Debug)]
+pub struct XPBDPlugin {
+    add_broadphase: fn(&mut App),
+    add_constraints: Vec<fn(&mut App)>,
+}
+
+impl XPBDPlugin {
+    /// Uses `B` to find collision pairs instead of the default [`DynamicAabbTree`].
+    pub fn with_broadphase<B: Broadphase + FromWorld>(mut self) -> Self {
+        self.add_broadphase = add_broadphase::<B>;
+        self
+    }
+
+    /// Solves every `C` component as a constraint in each substep, the joints in [`joints`] are
+    /// registered already.
+    pub fn with_constraint<C: PositionConstraint>(mut self) -> Self {
+        self.add_constraints.push(add_constraint::<C>);
+        self
+    }
+}
+
+impl Default for XPBDPlugin {
+    fn default() -> Self {
+        Self {
+            add_broadphase: add_broadphase::<DynamicAabbTree>,
+            add_constraints: vec![
+                add_constraint::<DistanceJoint>,
+                add_constraint::<RevoluteJoint>,
+                add_constraint::<PrismaticJoint>,
+                add_constraint::<FixedJoint>,
+            ],
+        }
+    }
+}
+
+fn add_broadphase<B: Broadphase + FromWorld>(app: &mut App) {
+    app.init_resource::<B>().add_systems(
+        PhysicsSchedule,
+        collect_collision_pairs::<B>.in_set(PhysicsSet::Broadphase),
+    );
+}
+
+fn add_constraint<C: PositionConstraint>(app: &mut App) {
+    app.add_systems(
+        SubstepSchedule,
+        (
+            clear_lagrange_multipliers::<C>.in_set(SubstepSet::Integrate),
+            solve_constraints::<C>.in_set(SubstepSet::SolveConstraints),
+        ),
+    )
+    .add_systems(
+        PhysicsSchedule,
+        break_constraints::<C>.in_set(PhysicsSet::Sync),
+    );
+}
 
 impl Plugin for XPBDPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Time::<Fixed>::from_seconds(DELTA_TIME.into()))
+        (self.add_broadphase)(app);
+        for add_constraint in self.add_constraints.iter() {
+            add_constraint(app);
+        }
+        app.init_resource::<PhysicsTimestep>()
+            .init_resource::<DeltaTime>()
             .init_resource::<Gravity>()
+            .init_resource::<SubstepCount>()
+            .init_resource::<SubDeltaTime>()
             .init_resource::<CollisionPairs>()
             .init_resource::<Contacts>()
-            .init_resource::<StaticContacts>()
+            .init_resource::<FluidSettings>()
+            .init_resource::<FluidNeighbors>()
+            .add_event::<JointBroken>()
+            .add_systems(
+                First,
+                sync_fixed_timestep.run_if(resource_changed::<PhysicsTimestep>),
+            )
+            .add_systems(FixedUpdate, run_physics_schedule.run_if(fixed_timestep))
+            .add_systems(Update, run_physics_schedule.run_if(not(fixed_timestep)))
+            .configure_sets(
+                PhysicsSchedule,
+                (
+                    PhysicsSet::Prepare,
+                    PhysicsSet::Broadphase,
+                    PhysicsSet::Substeps,
+                    PhysicsSet::Sync,
+                )
+                    .chain(),
+            )
+            .add_systems(
+                PhysicsSchedule,
+                (
+                    (switch_rigid_bodies, update_inertia, update_inverse_mass)
+                        .chain()
+                        .in_set(PhysicsSet::Prepare),
+                    find_fluid_neighbors.in_set(PhysicsSet::Broadphase),
+                    run_substeps.in_set(PhysicsSet::Substeps),
+                    (tear_distance_joints, sync_transform).in_set(PhysicsSet::Sync),
+                ),
+            )
+            .configure_sets(
+                SubstepSchedule,
+                (
+                    SubstepSet::Integrate,
+                    SubstepSet::SolvePos,
+                    SubstepSet::SolveConstraints,
+                    SubstepSet::UpdateVel,
+                    SubstepSet::SolveVel,
+                )
+                    .chain(),
+            )
             .add_systems(
-                FixedUpdate,
+                SubstepSchedule,
                 (
-                    collect_collision_pairs.before(integrate),
-                    integrate,
-                    clear_contacts.before(solve_pos),
-                    solve_pos.after(integrate),
-                    solve_pos_statics.after(integrate),
-                    solve_pos_static_boxes.after(integrate),
-                    update_velocity.after(solve_pos),
-                    solve_vel.after(update_velocity),
-                    solve_vel_statics.after(update_velocity),
-                    sync_transform.after(solve_vel),
+                    (integrate, integrate_rot, integrate_kinematic).in_set(SubstepSet::Integrate),
+                    (clear_contacts, solve_pos)
+                        .chain()
+                        .in_set(SubstepSet::SolvePos),
+                    (
+                        solve_area_constraints,
+                        solve_shape_matching,
+                        solve_fluid_density,
+                    )
+                        .in_set(SubstepSet::SolveConstraints),
+                    (update_velocity, update_angular_velocity).in_set(SubstepSet::UpdateVel),
+                    (solve_vel, apply_fluid_viscosity)
+                        .chain()
+                        .in_set(SubstepSet::SolveVel),
                 ),
             );
     }
 }
 
-fn sync_transform(mut query: Query<(&mut Transform, &Pos)>) {
-    for (mut transform, pos) in query.iter_mut() {
-        transform.translation = pos.0.extend(0.)
+fn fixed_timestep(timestep: Res<PhysicsTimestep>) -> bool {
+    matches!(*timestep, PhysicsTimestep::Fixed(_))
+}
+
+fn sync_fixed_timestep(timestep: Res<PhysicsTimestep>, mut time: ResMut<Time<Fixed>>) {
+    if let PhysicsTimestep::Fixed(dt) = *timestep {
+        time.set_timestep_seconds(dt.into());
+    }
+}
+
+fn run_physics_schedule(world: &mut World) {
+    let dt = match *world.resource::<PhysicsTimestep>() {
+        PhysicsTimestep::Fixed(dt) => dt,
+        PhysicsTimestep::Variable { max_dt } => {
+            world.resource::<Time>().delta_seconds().min(max_dt)
+        }
+    };
+    // The first frame has no delta yet
+    if dt <= 0. {
+        return;
+    }
+    world.resource_mut::<DeltaTime>().0 = dt;
+    world.run_schedule(PhysicsSchedule);
+}
+
+fn run_substeps(world: &mut World) {
+    // Zero substeps would make the substep infinitely long, run at least one
+    let substeps = world.resource::<SubstepCount>().0.max(1);
+    let dt = world.resource::<DeltaTime>().0;
+    world.resource_mut::<SubDeltaTime>().0 = dt / substeps as f32;
+    for _ in 0..substeps {
+        world.run_schedule(SubstepSchedule);
+    }
+}
+
+fn sync_transform(mut query: Query<(&mut Transform, &Pos, &Rot)>) {
+    for (mut transform, pos, rot) in query.iter_mut() {
+        transform.translation = pos.0.extend(0.);
+        transform.rotation = Quat::from_rotation_z(rot.0);
+    }
+}
+
+/// Starts bodies whose `RigidBody` changed from where they are, so the poses they had before the
+/// switch don't turn into a velocity. Static bodies also come to rest.
+#[allow(clippy::type_complexity)]
+fn switch_rigid_bodies(
+    mut bodies: Query<
+        (
+            &RigidBody,
+            (&Pos, Option<&mut PrevPos>),
+            (&Rot, Option<&mut PrevRot>),
+            Option<(&mut Velocity, &mut AngularVelocity)>,
+        ),
+        Changed<RigidBody>,
+    >,
+) {
+    for (rigid_body, (pos, prev_pos), (rot, prev_rot), vels) in bodies.iter_mut() {
+        if let Some(mut prev_pos) = prev_pos {
+            prev_pos.0 = pos.0;
+        }
+        if let Some(mut prev_rot) = prev_rot {
+            prev_rot.0 = rot.0;
+        }
+        if let (RigidBody::Static, Some((mut vel, mut ang_vel))) = (rigid_body, vels) {
+            vel.0 = Vec2::ZERO;
+            ang_vel.0 = 0.;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_inertia(
+    mut query: Query<
+        (&mut Inertia, &Mass, ColliderQuery),
+        Or<(
+            Changed<Mass>,
+            Changed<CircleCollider>,
+            Changed<BoxCollider>,
+            Changed<CapsuleCollider>,
+            Changed<ConvexPolygonCollider>,
+            Changed<PolylineCollider>,
+        )>,
+    >,
+) {
+    for (mut inertia, mass, collider) in query.iter_mut() {
+        if let Some(shape) = collider.shape() {
+            inertia.0 = shape.inertia(mass.0);
+        }
+    }
+}
+
+/// Bodies with a `Mass` that were spawned without the inverse components get them here, the
+/// solver would treat them as immovable otherwise.
+#[allow(clippy::type_complexity)]
+fn update_inverse_mass(
+    mut commands: Commands,
+    mut query: Query<
+        (
+            &RigidBody,
+            Option<(&Mass, &Inertia)>,
+            &mut InverseMass,
+            Option<&mut InverseInertia>,
+        ),
+        Or<(Changed<RigidBody>, Changed<Mass>, Changed<Inertia>)>,
+    >,
+    missing: Query<
+        (Entity, &RigidBody, &Mass, Option<&Inertia>),
+        Or<(Without<InverseMass>, Without<InverseInertia>)>,
+    >,
+) {
+    for (rigid_body, mass, mut inverse_mass, inverse_inertia) in query.iter_mut() {
+        let mass = mass.filter(|_| rigid_body.is_dynamic());
+        inverse_mass.0 = mass.map_or(0., |(mass, _)| inverse(mass.0));
+        if let Some(mut inverse_inertia) = inverse_inertia {
+            inverse_inertia.0 = mass.map_or(0., |(_, inertia)| inverse(inertia.0));
+        }
+    }
+    for (entity, rigid_body, mass, inertia) in missing.iter() {
+        let (inverse_mass, inverse_inertia) = if rigid_body.is_dynamic() {
+            (
+                inverse(mass.0),
+                inertia.map_or(0., |inertia| inverse(inertia.0)),
+            )
+        } else {
+            (0., 0.)
+        };
+        commands
+            .entity(entity)
+            .insert((InverseMass(inverse_mass), InverseInertia(inverse_inertia)));
+    }
+}
+
+/// Inverse of a mass or inertia, zero for a body without any, like a polygon with no area,
+/// which then can't be turned.
+fn inverse(value: f32) -> f32 {
+    if value > f32::EPSILON {
+        1. / value
+    } else {
+        0.
     }
 }
 
@@ -48,190 +338,269 @@ fn integrate(
         &mut Velocity,
         &mut PreSolveVel,
         &Mass,
+        &RigidBody,
     )>,
     gravity: Res<Gravity>,
+    sub_dt: Res<SubDeltaTime>,
 ) {
-    for (mut pos, mut prev_pos, mut vel, mut pre_solve_vel, mass) in query.iter_mut() {
+    for (mut pos, mut prev_pos, mut vel, mut pre_solve_vel, mass, rigid_body) in query.iter_mut() {
+        if !rigid_body.is_dynamic() {
+            continue;
+        }
         prev_pos.0 = pos.0;
         let gravitational_force = mass.0 * gravity.0;
         let external_forces = gravitational_force;
-        vel.0 += (external_forces / mass.0) * DELTA_TIME;
-        pos.0 += vel.0 * DELTA_TIME;
+        vel.0 += (external_forces / mass.0) * sub_dt.0;
+        pos.0 += vel.0 * sub_dt.0;
         pre_solve_vel.0 = vel.0;
     }
 }
 
-fn update_velocity(mut query: Query<(&mut Pos, &mut PrevPos, &mut Velocity)>) {
-    for (pos, prev_pos, mut vel) in query.iter_mut() {
-        vel.0 = (pos.0 - prev_pos.0) / DELTA_TIME;
+#[allow(clippy::type_complexity)]
+fn integrate_rot(
+    mut query: Query<(
+        &mut Rot,
+        &mut PrevRot,
+        &mut AngularVelocity,
+        &mut PreSolveAngularVelocity,
+        &InverseInertia,
+        Option<&ExternalTorque>,
+        &RigidBody,
+    )>,
+    sub_dt: Res<SubDeltaTime>,
+) {
+    for (
+        mut rot,
+        mut prev_rot,
+        mut ang_vel,
+        mut pre_solve_ang_vel,
+        inverse_inertia,
+        torque,
+        rigid_body,
+    ) in query.iter_mut()
+    {
+        if !rigid_body.is_dynamic() {
+            continue;
+        }
+        prev_rot.0 = rot.0;
+        let external_torque = torque.map_or(0., |torque| torque.0);
+        ang_vel.0 += external_torque * inverse_inertia.0 * sub_dt.0;
+        rot.0 += ang_vel.0 * sub_dt.0;
+        pre_solve_ang_vel.0 = ang_vel.0;
     }
 }
 
-fn collect_collision_pairs(
-    query: Query<(Entity, &Pos, &Velocity, &CircleCollider)>,
-    mut collision_pairs: ResMut<CollisionPairs>,
+#[allow(clippy::type_complexity)]
+fn integrate_kinematic(
+    mut query: Query<(
+        (&mut Pos, &mut PrevPos, &Velocity),
+        (&mut Rot, &mut PrevRot, &AngularVelocity),
+        &RigidBody,
+    )>,
+    sub_dt: Res<SubDeltaTime>,
 ) {
-    collision_pairs.0.clear();
-    let k = 2.;
-    let safety_margin_factor = k * DELTA_TIME;
-    let safety_margin_factor_sqr = safety_margin_factor.powi(2);
-    unsafe {
-        for (entity_a, pos_a, vel_a, circle_a) in query.iter_unsafe() {
-            let vel_a_sqr = vel_a.0.length_squared();
-            for (entity_b, pos_b, vel_b, circle_b) in query.iter_unsafe() {
-                if entity_a <= entity_b {
-                    continue;
-                }
-                let ab = pos_b.0 - pos_a.0;
-                let vel_b_sqr = vel_b.0.length_squared();
-                let safety_margin_sqr = safety_margin_factor_sqr * (vel_a_sqr + vel_b_sqr);
-                let combined_radius = circle_a.radius + circle_b.radius + safety_margin_sqr.sqrt();
-                let ab_sqr_len = ab.length_squared();
-                if ab_sqr_len < combined_radius.powi(2) {
-                    collision_pairs.0.push((entity_a, entity_b))
-                }
-            }
+    for ((mut pos, mut prev_pos, vel), (mut rot, mut prev_rot, ang_vel), rigid_body) in
+        query.iter_mut()
+    {
+        if *rigid_body != RigidBody::Kinematic {
+            continue;
         }
+        prev_pos.0 = pos.0;
+        pos.0 += vel.0 * sub_dt.0;
+        prev_rot.0 = rot.0;
+        rot.0 += ang_vel.0 * sub_dt.0;
     }
 }
-fn solve_pos(
-    query: Query<(&mut Pos, &CircleCollider, &Mass)>,
-    collision_pairs: ResMut<CollisionPairs>,
+
+fn update_velocity(
+    mut query: Query<(&mut Pos, &mut PrevPos, &mut Velocity)>,
+    sub_dt: Res<SubDeltaTime>,
 ) {
-    for (entity_a, entity_b) in collision_pairs.0.iter() {
-        let ((mut pos_a, circle_a, mass_a), (mut pos_b, circle_b, mass_b)) = unsafe {
-            assert!(entity_a != entity_b);
-            (
-                query.get_unchecked(*entity_a).unwrap(),
-                query.get_unchecked(*entity_b).unwrap(),
-            )
-        };
+    for (pos, prev_pos, mut vel) in query.iter_mut() {
+        vel.0 = (pos.0 - prev_pos.0) / sub_dt.0;
+    }
+}
 
-        let ab = pos_b.0 - pos_a.0;
-        let combined_radius = circle_a.radius + circle_b.radius;
-        if ab.length_squared() < combined_radius.powi(2) {
-            let penetration_depth = combined_radius - ab.length();
-            let n = ab.normalize();
-            let w_a = 1. / mass_a.0;
-            let w_b = 1. / mass_b.0;
-            let w_sum = w_a + w_b;
-            pos_a.0 -= n * penetration_depth * w_a / w_sum;
-            pos_b.0 += n * penetration_depth * w_b / w_sum;
-        }
+fn update_angular_velocity(
+    mut query: Query<(&Rot, &PrevRot, &mut AngularVelocity)>,
+    sub_dt: Res<SubDeltaTime>,
+) {
+    for (rot, prev_rot, mut ang_vel) in query.iter_mut() {
+        ang_vel.0 = (rot.0 - prev_rot.0) / sub_dt.0;
     }
 }
-fn solve_vel(
-    query: Query<(&mut Velocity, &PreSolveVel, &Mass, &Restitution)>,
-    contacts: Res<Contacts>,
+
+fn solve_pos(
+    mut bodies: Query<(PoseQuery, &Friction, ColliderQuery)>,
+    collision_pairs: Res<CollisionPairs>,
+    mut contacts: ResMut<Contacts>,
+    sub_dt: Res<SubDeltaTime>,
 ) {
-    for (entity_a, entity_b, n) in contacts.0.iter().cloned() {
-        let (
-            (mut vel_a, pre_solve_vel_a, mass_a, restitution_a),
-            (mut vel_b, pre_solve_vel_b, mass_b, restitution_b),
-        ) = unsafe {
-            // Ensure safety
-            assert!(entity_a != entity_b);
-            (
-                query.get_unchecked(entity_a).unwrap(),
-                query.get_unchecked(entity_b).unwrap(),
-            )
+    for (entity_a, entity_b) in collision_pairs.0.iter().copied() {
+        let Ok([(mut pose_a, friction_a, collider_a), (mut pose_b, friction_b, collider_b)]) =
+            bodies.get_many_mut([entity_a, entity_b])
+        else {
+            continue;
+        };
+        let (Some(shape_a), Some(shape_b)) = (collider_a.shape(), collider_b.shape()) else {
+            continue;
         };
-        let pre_solve_relative_vel = pre_solve_vel_a.0 - pre_solve_vel_b.0;
-        let pre_solve_normal_vel = Vec2::dot(pre_solve_relative_vel, n);
 
-        let relative_vel = vel_a.0 - vel_b.0;
-        let normal_vel = Vec2::dot(relative_vel, n);
-        let restitution = (restitution_a.0 + restitution_b.0) / 2.;
+        // Statics and kinematics have an inverse mass of zero, a kinematic body moved since the
+        // previous substep, which carries what rests on it
+        let mut body_a = pose_a.pose_body();
+        let mut body_b = pose_b.pose_body();
+        let manifold = collide(
+            *body_a.pos,
+            *body_a.rot,
+            shape_a,
+            *body_b.pos,
+            *body_b.rot,
+            shape_b,
+        );
+        let static_friction = (friction_a.static_coefficient + friction_b.static_coefficient) / 2.;
+        let solved = solve_manifold_pos(
+            &mut body_a,
+            &mut body_b,
+            &manifold,
+            static_friction,
+            sub_dt.0,
+        );
+        for (contact, normal_lagrange) in solved {
+            contacts.0.push(ContactConstraint {
+                entity_a,
+                entity_b,
+                contact,
+                normal_lagrange,
+            });
+        }
+    }
+}
 
-        let w_a = 1. / mass_a.0;
-        let w_b = 1. / mass_b.0;
-        let w_sum = w_a + w_b;
+/// Velocities and inverse mass of a body in contact.
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct VelocityQuery {
+    vel: Option<&'static mut Velocity>,
+    ang_vel: Option<&'static mut AngularVelocity>,
+    pre_solve_vel: Option<&'static PreSolveVel>,
+    pre_solve_ang_vel: Option<&'static PreSolveAngularVelocity>,
+    inverse_mass: Option<&'static InverseMass>,
+    inverse_inertia: Option<&'static InverseInertia>,
+    restitution: &'static Restitution,
+    friction: &'static Friction,
+}
 
-        let restitution_velocity = (-restitution * pre_solve_normal_vel).min(0.);
-        let vel_impulse = n * ((-normal_vel + restitution_velocity) / w_sum);
+impl VelocityQueryItem<'_> {
+    /// Bodies without velocities are at rest.
+    fn velocities(&self) -> (Vec2, f32) {
+        (
+            self.vel.as_ref().map_or(Vec2::ZERO, |vel| vel.0),
+            self.ang_vel.as_ref().map_or(0., |ang_vel| ang_vel.0),
+        )
+    }
 
-        vel_a.0 += vel_impulse * w_a;
-        vel_b.0 -= vel_impulse * w_b;
+    /// Solves on a copy of the velocities, bodies the solver can't move keep their velocity
+    /// through the substep.
+    fn velocity_body<'a>(&self, vel: &'a mut Vec2, ang_vel: &'a mut f32) -> VelocityBody<'a> {
+        let inverse_mass = self.inverse_mass.map_or(0., |inverse_mass| inverse_mass.0);
+        let inverse_inertia = self
+            .inverse_inertia
+            .map_or(0., |inverse_inertia| inverse_inertia.0);
+        let (pre_solve_vel, pre_solve_ang_vel) = match (self.pre_solve_vel, self.pre_solve_ang_vel)
+        {
+            (Some(pre_solve_vel), Some(pre_solve_ang_vel)) if inverse_mass > 0. => {
+                (pre_solve_vel.0, pre_solve_ang_vel.0)
+            }
+            _ => (*vel, *ang_vel),
+        };
+        VelocityBody {
+            vel,
+            ang_vel,
+            pre_solve_vel,
+            pre_solve_ang_vel,
+            inverse_mass,
+            inverse_inertia,
+        }
     }
-}
 
-fn solve_vel_statics(
-    mut dynamics: Query<(&mut Velocity, &PreSolveVel, &Restitution), With<Mass>>,
-    statics: Query<&Restitution, Without<Mass>>,
-    contacts: Res<StaticContacts>,
-) {
-    for (entity_a, entity_b, n) in contacts.0.iter().cloned() {
-        let (mut vel_a, pre_solve_vel_a, restitution_a) = dynamics.get_mut(entity_a).unwrap();
-        let restitution_b = statics.get(entity_b).unwrap();
-        let pre_solve_normal_vel = Vec2::dot(pre_solve_vel_a.0, n);
-        let normal_vel = Vec2::dot(vel_a.0, n);
-        let restitution = (restitution_a.0 + restitution_b.0) / 2.;
-        vel_a.0 += n * (-normal_vel + (-restitution * pre_solve_normal_vel).min(0.));
+    fn set_velocities(&mut self, (vel, ang_vel): (Vec2, f32)) {
+        if let Some(body_vel) = self.vel.as_mut() {
+            body_vel.0 = vel;
+        }
+        if let Some(body_ang_vel) = self.ang_vel.as_mut() {
+            body_ang_vel.0 = ang_vel;
+        }
     }
 }
 
-fn solve_pos_statics(
-    mut dynamics: Query<(Entity, &mut Pos, &CircleCollider), With<Mass>>,
-    statics: Query<(Entity, &Pos, &CircleCollider), Without<Mass>>,
-    mut contacts: ResMut<StaticContacts>,
-) {
-    for (entity_a, mut pos_a, circle_a) in dynamics.iter_mut() {
-        for (entity_b, pos_b, circle_b) in statics.iter() {
-            let ab = pos_b.0 - pos_a.0;
-            let combined_radius = circle_a.radius + circle_b.radius;
-            let ab_sqr_len = ab.length_squared();
-            if ab_sqr_len < combined_radius * combined_radius {
-                let ab_length = ab_sqr_len.sqrt();
-                let penetration_depth = combined_radius - ab_length;
-                let n = ab / ab_length;
-                pos_a.0 -= n * penetration_depth;
-                contacts.0.push((entity_a, entity_b, n));
-            }
-        }
+fn solve_vel(mut bodies: Query<VelocityQuery>, contacts: Res<Contacts>, sub_dt: Res<SubDeltaTime>) {
+    for constraint in contacts.0.iter() {
+        let Ok([mut body_a, mut body_b]) =
+            bodies.get_many_mut([constraint.entity_a, constraint.entity_b])
+        else {
+            continue;
+        };
+        let restitution = (body_a.restitution.0 + body_b.restitution.0) / 2.;
+        let dynamic_friction =
+            (body_a.friction.dynamic_coefficient + body_b.friction.dynamic_coefficient) / 2.;
+        let (mut vel_a, mut ang_vel_a) = body_a.velocities();
+        let (mut vel_b, mut ang_vel_b) = body_b.velocities();
+        solve_contact_vel(
+            &mut body_a.velocity_body(&mut vel_a, &mut ang_vel_a),
+            &mut body_b.velocity_body(&mut vel_b, &mut ang_vel_b),
+            constraint,
+            restitution,
+            dynamic_friction,
+            sub_dt.0,
+        );
+        body_a.set_velocities((vel_a, ang_vel_a));
+        body_b.set_velocities((vel_b, ang_vel_b));
     }
 }
 
-fn clear_contacts(mut contacts: ResMut<Contacts>, mut static_contacts: ResMut<StaticContacts>) {
+fn clear_contacts(mut contacts: ResMut<Contacts>) {
     contacts.0.clear();
-    static_contacts.0.clear();
 }
 
-fn solve_pos_static_boxes(
-    mut dynamics: Query<(Entity, &mut Pos, &CircleCollider), With<Mass>>,
-    statics: Query<(Entity, &Pos, &BoxCollider), Without<Mass>>,
-    mut contacts: ResMut<StaticContacts>,
-) {
-    for (entity_a, mut pos_a, circle_a) in dynamics.iter_mut() {
-        for (entity_b, pos_b, box_b) in statics.iter() {
-            let box_to_circle = pos_a.0 - pos_b.0;
-            let box_to_circle_abs = box_to_circle.abs();
-            let half_extents = box_b.size / 2.;
-            let corner_to_center = box_to_circle_abs - half_extents;
-            let r = circle_a.radius;
-            if corner_to_center.x > r || corner_to_center.y > r {
-                continue;
-            }
-            let s = box_to_circle.signum();
-
-            let (n, penetration_depth) = if corner_to_center.x > 0. && corner_to_center.y > 0. {
-                // Corner case
-                let corner_to_center_sqr = corner_to_center.length_squared();
-                if corner_to_center_sqr > r * r {
-                    continue;
-                }
-                let corner_dist = corner_to_center_sqr.sqrt();
-                let penetration_depth = r - corner_dist;
-                let n = corner_to_center / corner_dist * -s;
-                (n, penetration_depth)
-            } else if corner_to_center.x > corner_to_center.y {
-                // Closer to vertical edge
-                (Vec2::X * -s.x, -corner_to_center.x + r)
-            } else {
-                (Vec2::Y * -s.y, -corner_to_center.y + r)
-            };
-
-            pos_a.0 -= n * penetration_depth;
-            contacts.0.push((entity_a, entity_b, n));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::DynamicConvexPolygonBundle;
+
+    #[test]
+    fn degenerate_polygon_rotation_stays_finite() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugins(XPBDPlugin::default());
+        app.finish();
+        app.cleanup();
+        // Vertices on one line enclose no area, so the polygon has no inertia
+        let body = app
+            .world
+            .spawn((
+                DynamicConvexPolygonBundle {
+                    collider: ConvexPolygonCollider::new(vec![
+                        Vec2::new(-1., 0.),
+                        Vec2::new(0., 0.),
+                        Vec2::new(1., 0.),
+                    ]),
+                    ..DynamicConvexPolygonBundle::new_with_pos_and_vel(Vec2::ZERO, Vec2::X)
+                },
+                ExternalTorque(1.),
+            ))
+            .id();
+        for _ in 0..10 {
+            app.world.run_schedule(FixedUpdate);
         }
+        assert_eq!(app.world.get::<Inertia>(body).unwrap().0, 0.);
+        assert!(app.world.get::<Rot>(body).unwrap().0.is_finite());
+        assert!(app
+            .world
+            .get::<AngularVelocity>(body)
+            .unwrap()
+            .0
+            .is_finite());
+        assert!(app.world.get::<Pos>(body).unwrap().0.is_finite());
     }
 }
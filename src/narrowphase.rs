@@ -0,0 +1,801 @@
+use std::f32::consts::PI;
+
+use bevy::{ecs::query::QueryData, prelude::*, utils::smallvec::SmallVec};
+
+use crate::broadphase::Aabb;
+use crate::components::*;
+
+/// Penetration between two shapes, the normal points from the first shape into the second one.
+///
+/// The contact points are relative to the centers of the shapes but not rotated into their local
+/// space.
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub normal: Vec2,
+    pub point_a: Vec2,
+    pub point_b: Vec2,
+    pub penetration_depth: f32,
+}
+
+impl Contact {
+    fn flipped(self) -> Self {
+        Self {
+            normal: -self.normal,
+            point_a: self.point_b,
+            point_b: self.point_a,
+            ..self
+        }
+    }
+}
+
+/// Contact points between two shapes, boxes resting on a face get one for each corner.
+pub type ContactManifold = SmallVec<[Contact; 2]>;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Shape<'a> {
+    Circle { radius: f32 },
+    Box { half_extents: Vec2 },
+    Capsule { half_height: f32, radius: f32 },
+    Polygon { vertices: &'a [Vec2] },
+    Polyline { vertices: &'a [Vec2], closed: bool },
+}
+
+impl Shape<'_> {
+    pub fn aabb(&self, pos: Vec2, rot: f32) -> Aabb {
+        match *self {
+            Shape::Circle { radius } => Aabb::from_center_half_extents(pos, Vec2::splat(radius)),
+            Shape::Box { half_extents } => {
+                let (sin, cos) = rot.sin_cos();
+                let rotated_half_extents = Vec2::new(
+                    cos.abs() * half_extents.x + sin.abs() * half_extents.y,
+                    sin.abs() * half_extents.x + cos.abs() * half_extents.y,
+                );
+                Aabb::from_center_half_extents(pos, rotated_half_extents)
+            }
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => {
+                let half_segment = Vec2::from_angle(rot).rotate(Vec2::Y * half_height).abs();
+                Aabb::from_center_half_extents(pos, half_segment + radius)
+            }
+            Shape::Polygon { vertices } | Shape::Polyline { vertices, .. } => {
+                let rotation = Vec2::from_angle(rot);
+                let (min, max) = vertices.iter().fold(
+                    (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+                    |(min, max), vertex| {
+                        let vertex = rotation.rotate(*vertex);
+                        (min.min(vertex), max.max(vertex))
+                    },
+                );
+                Aabb::new(pos + min, pos + max)
+            }
+        }
+    }
+
+    /// Moment of inertia around the center for a body of uniform density.
+    pub fn inertia(&self, mass: f32) -> f32 {
+        match *self {
+            Shape::Circle { radius } => 0.5 * mass * radius * radius,
+            Shape::Box { half_extents } => mass * 4. * half_extents.length_squared() / 12.,
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => {
+                // A rectangle between the caps, and the two halves of a disc whose centroids sit
+                // past the ends of the segment
+                let rectangle_area = 4. * radius * half_height;
+                let disc_area = PI * radius * radius;
+                let rectangle_mass = mass * rectangle_area / (rectangle_area + disc_area);
+                let disc_mass = mass - rectangle_mass;
+                let cap_centroid = 4. * radius / (3. * PI);
+                rectangle_mass * (radius * radius + half_height * half_height) / 3.
+                    + disc_mass
+                        * (radius * radius / 2.
+                            + half_height * half_height
+                            + 2. * half_height * cap_centroid)
+            }
+            Shape::Polygon { vertices } => {
+                // Sum over the triangles between the center and each edge
+                let n = vertices.len();
+                let (weighted, area) = (0..n).fold((0., 0.), |(weighted, area), i| {
+                    let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+                    let cross = a.perp_dot(b);
+                    (
+                        weighted + cross * (a.dot(a) + a.dot(b) + b.dot(b)),
+                        area + cross,
+                    )
+                });
+                if area <= f32::EPSILON {
+                    return 0.;
+                }
+                mass * weighted / (6. * area)
+            }
+            Shape::Polyline { vertices, closed } => {
+                // A thin wire, each segment weighs as much as it is long
+                let n = vertices.len();
+                let segments = if closed { n } else { n.saturating_sub(1) };
+                let (weighted, length) = (0..segments).fold((0., 0.), |(weighted, length), i| {
+                    let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+                    let segment_length = a.distance(b);
+                    (
+                        weighted + segment_length * (a.dot(a) + a.dot(b) + b.dot(b)) / 3.,
+                        length + segment_length,
+                    )
+                });
+                if length <= f32::EPSILON {
+                    return 0.;
+                }
+                mass * weighted / length
+            }
+        }
+    }
+
+    /// Vertices in world space and the radius they are rounded by, for every shape but circles
+    /// and polylines.
+    fn rounded_polygon(&self, pos: Vec2, rot: f32) -> Option<(SmallVec<[Vec2; 8]>, f32)> {
+        match *self {
+            Shape::Circle { .. } | Shape::Polyline { .. } => None,
+            Shape::Box { half_extents } => Some((
+                box_vertices(pos, rot, half_extents).into_iter().collect(),
+                0.,
+            )),
+            Shape::Capsule {
+                half_height,
+                radius,
+            } => Some((
+                capsule_vertices(pos, rot, half_height)
+                    .into_iter()
+                    .collect(),
+                radius,
+            )),
+            Shape::Polygon { vertices } => {
+                let rotation = Vec2::from_angle(rot);
+                let vertices = vertices
+                    .iter()
+                    .map(|vertex| pos + rotation.rotate(*vertex))
+                    .collect();
+                Some((vertices, 0.))
+            }
+        }
+    }
+
+    /// A capsule without a segment is a circle, which keeps its edges from having no normal.
+    fn simplified(self) -> Self {
+        match self {
+            Shape::Capsule {
+                half_height,
+                radius,
+            } if half_height <= f32::EPSILON => Shape::Circle { radius },
+            shape => shape,
+        }
+    }
+}
+
+/// Collider components of an entity, whichever of them it has.
+#[derive(QueryData)]
+pub struct ColliderQuery {
+    circle: Option<&'static CircleCollider>,
+    box_collider: Option<&'static BoxCollider>,
+    capsule: Option<&'static CapsuleCollider>,
+    polygon: Option<&'static ConvexPolygonCollider>,
+    polyline: Option<&'static PolylineCollider>,
+}
+
+impl ColliderQueryItem<'_> {
+    pub fn shape(&self) -> Option<Shape<'_>> {
+        if let Some(circle) = self.circle {
+            Some(Shape::Circle {
+                radius: circle.radius,
+            })
+        } else if let Some(box_collider) = self.box_collider {
+            Some(Shape::Box {
+                half_extents: box_collider.size / 2.,
+            })
+        } else if let Some(capsule) = self.capsule {
+            Some(Shape::Capsule {
+                half_height: capsule.half_height,
+                radius: capsule.radius,
+            })
+        } else if let Some(polygon) = self.polygon {
+            (polygon.vertices.len() >= 3).then_some(Shape::Polygon {
+                vertices: &polygon.vertices,
+            })
+        } else {
+            self.polyline
+                .filter(|polyline| polyline.vertices.len() >= 2)
+                .map(|polyline| Shape::Polyline {
+                    vertices: &polyline.vertices,
+                    closed: polyline.closed,
+                })
+        }
+    }
+}
+
+/// Signed area of a polygon, positive when it is counter-clockwise.
+pub fn polygon_area(vertices: &[Vec2]) -> f32 {
+    let n = vertices.len();
+    (0..n)
+        .map(|i| vertices[i].perp_dot(vertices[(i + 1) % n]))
+        .sum::<f32>()
+        / 2.
+}
+
+/// Contacts between two shapes, anything but circles is treated as a rounded polygon so every
+/// shape collides with every other one. Polylines collide one segment at a time, and not with
+/// each other.
+pub fn collide(
+    pos_a: Vec2,
+    rot_a: f32,
+    shape_a: Shape,
+    pos_b: Vec2,
+    rot_b: f32,
+    shape_b: Shape,
+) -> ContactManifold {
+    match (shape_a.simplified(), shape_b.simplified()) {
+        (Shape::Polyline { .. }, Shape::Polyline { .. }) => ContactManifold::new(),
+        (Shape::Polyline { vertices, closed }, shape) => {
+            polyline_shape(pos_a, rot_a, vertices, closed, pos_b, rot_b, shape)
+        }
+        (shape, Shape::Polyline { vertices, closed }) => {
+            polyline_shape(pos_b, rot_b, vertices, closed, pos_a, rot_a, shape)
+                .into_iter()
+                .map(Contact::flipped)
+                .collect()
+        }
+        (Shape::Circle { radius: radius_a }, Shape::Circle { radius: radius_b }) => {
+            circle_circle(pos_a, radius_a, pos_b, radius_b)
+                .into_iter()
+                .collect()
+        }
+        (Shape::Circle { radius }, Shape::Box { half_extents }) => {
+            circle_box(pos_a, radius, pos_b, rot_b, half_extents)
+                .into_iter()
+                .collect()
+        }
+        (Shape::Box { half_extents }, Shape::Circle { radius }) => {
+            circle_box(pos_b, radius, pos_a, rot_a, half_extents)
+                .map(Contact::flipped)
+                .into_iter()
+                .collect()
+        }
+        (Shape::Circle { radius }, shape) => {
+            circle_rounded_polygon(pos_a, radius, pos_b, rot_b, shape)
+                .into_iter()
+                .collect()
+        }
+        (shape, Shape::Circle { radius }) => {
+            circle_rounded_polygon(pos_b, radius, pos_a, rot_a, shape)
+                .map(Contact::flipped)
+                .into_iter()
+                .collect()
+        }
+        (shape_a, shape_b) => {
+            let (Some((vertices_a, radius_a)), Some((vertices_b, radius_b))) = (
+                shape_a.rounded_polygon(pos_a, rot_a),
+                shape_b.rounded_polygon(pos_b, rot_b),
+            ) else {
+                return ContactManifold::new();
+            };
+            rounded_polygon_polygon(&vertices_a, pos_a, radius_a, &vertices_b, pos_b, radius_b)
+        }
+    }
+}
+
+pub fn circle_circle(pos_a: Vec2, radius_a: f32, pos_b: Vec2, radius_b: f32) -> Option<Contact> {
+    let ab = pos_b - pos_a;
+    let combined_radius = radius_a + radius_b;
+    let ab_sqr_len = ab.length_squared();
+    if ab_sqr_len >= combined_radius * combined_radius {
+        return None;
+    }
+    let ab_length = ab_sqr_len.sqrt();
+    // Pick any direction when the centers coincide
+    let normal = ab.try_normalize().unwrap_or(Vec2::Y);
+    Some(Contact {
+        normal,
+        point_a: normal * radius_a,
+        point_b: -normal * radius_b,
+        penetration_depth: combined_radius - ab_length,
+    })
+}
+
+pub fn circle_box(
+    pos_circle: Vec2,
+    radius: f32,
+    pos_box: Vec2,
+    rot_box: f32,
+    half_extents: Vec2,
+) -> Option<Contact> {
+    let rotation = Vec2::from_angle(rot_box);
+    // Work in the space of the box, where it is axis aligned
+    let box_to_circle = Vec2::from_angle(-rot_box).rotate(pos_circle - pos_box);
+    let box_to_circle_abs = box_to_circle.abs();
+    let corner_to_center = box_to_circle_abs - half_extents;
+    let r = radius;
+    if corner_to_center.x > r || corner_to_center.y > r {
+        return None;
+    }
+    let s = box_to_circle.signum();
+
+    let (n, penetration_depth) = if corner_to_center.x > 0. && corner_to_center.y > 0. {
+        // Corner case
+        let corner_to_center_sqr = corner_to_center.length_squared();
+        if corner_to_center_sqr > r * r {
+            return None;
+        }
+        let corner_dist = corner_to_center_sqr.sqrt();
+        let penetration_depth = r - corner_dist;
+        let n = corner_to_center / corner_dist * -s;
+        (n, penetration_depth)
+    } else if corner_to_center.x > corner_to_center.y {
+        // Closer to vertical edge
+        (Vec2::X * -s.x, -corner_to_center.x + r)
+    } else {
+        (Vec2::Y * -s.y, -corner_to_center.y + r)
+    };
+
+    let normal = rotation.rotate(n);
+    Some(Contact {
+        normal,
+        point_a: normal * r,
+        point_b: pos_circle - pos_box + normal * (r - penetration_depth),
+        penetration_depth,
+    })
+}
+
+/// Corners of a box in counter-clockwise order.
+fn box_vertices(pos: Vec2, rot: f32, half_extents: Vec2) -> [Vec2; 4] {
+    let rotation = Vec2::from_angle(rot);
+    [
+        Vec2::new(-half_extents.x, -half_extents.y),
+        Vec2::new(half_extents.x, -half_extents.y),
+        Vec2::new(half_extents.x, half_extents.y),
+        Vec2::new(-half_extents.x, half_extents.y),
+    ]
+    .map(|corner| pos + rotation.rotate(corner))
+}
+
+/// Ends of the segment of a capsule, which is a polygon with two vertices rounded by its radius.
+fn capsule_vertices(pos: Vec2, rot: f32, half_height: f32) -> [Vec2; 2] {
+    let half_segment = Vec2::from_angle(rot).rotate(Vec2::Y * half_height);
+    [pos - half_segment, pos + half_segment]
+}
+
+/// Outward normal of the edge starting at vertex `i` of a counter-clockwise polygon.
+fn edge_normal(vertices: &[Vec2], i: usize) -> Vec2 {
+    let edge = vertices[(i + 1) % vertices.len()] - vertices[i];
+    Vec2::new(edge.y, -edge.x).normalize_or_zero()
+}
+
+/// Edge of `a` along which `b` is the least deep, and how far `b` is from it.
+fn max_separation(a: &[Vec2], b: &[Vec2]) -> (usize, f32) {
+    (0..a.len())
+        .map(|i| {
+            let n = edge_normal(a, i);
+            let separation = b.iter().map(|v| n.dot(*v - a[i])).fold(f32::MAX, f32::min);
+            (i, separation)
+        })
+        .fold((0, f32::MIN), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        })
+}
+
+/// Keeps the part of the segment behind the plane `normal · x = offset`.
+fn clip_segment(points: [Vec2; 2], normal: Vec2, offset: f32) -> Option<[Vec2; 2]> {
+    let d0 = normal.dot(points[0]) - offset;
+    let d1 = normal.dot(points[1]) - offset;
+    match (d0 <= 0., d1 <= 0.) {
+        (true, true) => Some(points),
+        (false, false) => None,
+        _ => {
+            let intersection = points[0] + (points[1] - points[0]) * (d0 / (d0 - d1));
+            if d0 <= 0. {
+                Some([points[0], intersection])
+            } else {
+                Some([intersection, points[1]])
+            }
+        }
+    }
+}
+
+/// Closest points between the segments from `p1` to `q1` and from `p2` to `q2`, with how far
+/// along each segment they are from zero to one.
+fn segment_closest_points(p1: Vec2, q1: Vec2, p2: Vec2, q2: Vec2) -> (Vec2, f32, Vec2, f32) {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.length_squared();
+    let e = d2.length_squared();
+    let f = d2.dot(r);
+    let (s, t) = if a <= f32::EPSILON && e <= f32::EPSILON {
+        (0., 0.)
+    } else if a <= f32::EPSILON {
+        (0., (f / e).clamp(0., 1.))
+    } else if e <= f32::EPSILON {
+        ((-d1.dot(r) / a).clamp(0., 1.), 0.)
+    } else {
+        let b = d1.dot(d2);
+        let c = d1.dot(r);
+        // Parallel segments touch all along their overlap, any point of it will do
+        let denominator = a * e - b * b;
+        let s = if denominator > f32::EPSILON {
+            ((b * f - c * e) / denominator).clamp(0., 1.)
+        } else {
+            0.
+        };
+        let t = (b * s + f) / e;
+        if t < 0. {
+            ((-c / a).clamp(0., 1.), 0.)
+        } else if t > 1. {
+            (((b - c) / a).clamp(0., 1.), 1.)
+        } else {
+            (s, t)
+        }
+    };
+    (p1 + d1 * s, s, p2 + d2 * t, t)
+}
+
+/// Closest point to `point` on the segment from `start` to `end`.
+fn closest_point_on_segment(point: Vec2, start: Vec2, end: Vec2) -> Vec2 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    if length_squared <= f32::EPSILON {
+        return start;
+    }
+    start + segment * ((point - start).dot(segment) / length_squared).clamp(0., 1.)
+}
+
+/// Contact between a circle and any shape but a circle, from the closest point on the outline
+/// of the polygon, or from its closest edge when the center of the circle is inside it.
+fn circle_rounded_polygon(
+    pos_circle: Vec2,
+    radius: f32,
+    pos_polygon: Vec2,
+    rot_polygon: f32,
+    polygon: Shape,
+) -> Option<Contact> {
+    let (vertices, polygon_radius) = polygon.rounded_polygon(pos_polygon, rot_polygon)?;
+    let n = vertices.len();
+    let (edge, separation) = (0..n)
+        .map(|i| (i, edge_normal(&vertices, i).dot(pos_circle - vertices[i])))
+        .fold((0, f32::MIN), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        });
+    // A segment has no inside, its two edges face away from each other
+    if separation <= 0. && n > 2 {
+        let normal = -edge_normal(&vertices, edge);
+        return Some(Contact {
+            normal,
+            point_a: normal * radius,
+            point_b: pos_circle - pos_polygon + normal * separation - normal * polygon_radius,
+            penetration_depth: radius + polygon_radius - separation,
+        });
+    }
+    // The circle touches the polygon like a circle centered on the closest point of its outline
+    let closest = (0..n)
+        .map(|i| closest_point_on_segment(pos_circle, vertices[i], vertices[(i + 1) % n]))
+        .min_by(|a, b| {
+            a.distance_squared(pos_circle)
+                .total_cmp(&b.distance_squared(pos_circle))
+        })?;
+    circle_circle(pos_circle, radius, closest, polygon_radius).map(|contact| Contact {
+        point_b: contact.point_b + closest - pos_polygon,
+        ..contact
+    })
+}
+
+/// Separating axis test between two convex counter-clockwise polygons given in world space,
+/// clipping the incident edge against the reference edge to get up to two contact points.
+pub fn polygon_polygon(a: &[Vec2], center_a: Vec2, b: &[Vec2], center_b: Vec2) -> ContactManifold {
+    rounded_polygon_polygon(a, center_a, 0., b, center_b, 0.)
+}
+
+/// Like [`polygon_polygon`] for polygons whose outline is grown by a radius, which makes a
+/// segment with two vertices a capsule.
+pub fn rounded_polygon_polygon(
+    a: &[Vec2],
+    center_a: Vec2,
+    radius_a: f32,
+    b: &[Vec2],
+    center_b: Vec2,
+    radius_b: f32,
+) -> ContactManifold {
+    let mut manifold = ContactManifold::new();
+    let radius = radius_a + radius_b;
+    let (edge_a, separation_a) = max_separation(a, b);
+    if separation_a > radius {
+        return manifold;
+    }
+    let (edge_b, separation_b) = max_separation(b, a);
+    if separation_b > radius {
+        return manifold;
+    }
+
+    // Prefer `a` as the reference unless `b` is clearly better so the manifold doesn't flicker
+    // between the two when they are close
+    let flip = separation_b > 0.95 * separation_a + 0.01;
+    let (reference, incident, reference_edge) = if flip { (b, a, edge_b) } else { (a, b, edge_a) };
+    let (reference_center, reference_radius, incident_center, incident_radius) = if flip {
+        (center_b, radius_b, center_a, radius_a)
+    } else {
+        (center_a, radius_a, center_b, radius_b)
+    };
+
+    let n = edge_normal(reference, reference_edge);
+    let v1 = reference[reference_edge];
+    let v2 = reference[(reference_edge + 1) % reference.len()];
+    let incident_edge = (0..incident.len())
+        .min_by(|i, j| {
+            let dot_i = edge_normal(incident, *i).dot(n);
+            let dot_j = edge_normal(incident, *j).dot(n);
+            dot_i.total_cmp(&dot_j)
+        })
+        .unwrap_or(0);
+    let incident_points = [
+        incident[incident_edge],
+        incident[(incident_edge + 1) % incident.len()],
+    ];
+    let mut push = |reference_point: Vec2, incident_point: Vec2, normal: Vec2, depth: f32| {
+        let contact = Contact {
+            normal,
+            point_a: reference_point - reference_center,
+            point_b: incident_point - incident_center,
+            penetration_depth: depth,
+        };
+        manifold.push(if flip { contact.flipped() } else { contact });
+    };
+
+    let (p, s, q, t) = segment_closest_points(v1, v2, incident_points[0], incident_points[1]);
+    let distance = p.distance(q);
+    let closest_normal = (q - p).try_normalize().unwrap_or(n);
+    // When only the rounded outlines overlap, two corners push each other along the line
+    // between them rather than along a face normal
+    if separation_a.max(separation_b) > 0. {
+        if distance > radius {
+            return manifold;
+        }
+        if (s == 0. || s == 1.) && (t == 0. || t == 1.) {
+            push(
+                p + closest_normal * reference_radius,
+                q - closest_normal * incident_radius,
+                closest_normal,
+                radius - distance,
+            );
+            return manifold;
+        }
+    }
+
+    let tangent = (v2 - v1).normalize_or_zero();
+    let clipped = clip_segment(incident_points, -tangent, -tangent.dot(v1))
+        .and_then(|points| clip_segment(points, tangent, tangent.dot(v2)));
+
+    let mut touching = false;
+    for point in clipped.into_iter().flatten() {
+        let separation = n.dot(point - v1);
+        if separation > radius {
+            continue;
+        }
+        let reference_point = point - n * separation + n * reference_radius;
+        push(
+            reference_point,
+            point - n * incident_radius,
+            n,
+            radius - separation,
+        );
+        touching = true;
+    }
+    // Segments on one line, like capsules stacked end to end, touch past the sides of the
+    // reference face where clipping leaves nothing
+    if !touching && distance < radius {
+        push(
+            p + closest_normal * reference_radius,
+            q - closest_normal * incident_radius,
+            closest_normal,
+            radius - distance,
+        );
+    }
+    manifold
+}
+
+/// Contacts between a chain of one-sided segments and any other shape, the normals point from
+/// the chain into the shape.
+///
+/// Each segment collides as a polygon with two vertices. Where the shape touches a vertex
+/// between two segments, the contact is only kept when its normal lies between the normals of
+/// the two faces around a convex corner. On a flat seam or in a valley those faces already push
+/// the shape out, and the ghost contact with the end of the next segment would snag it.
+fn polyline_shape(
+    pos_polyline: Vec2,
+    rot_polyline: f32,
+    vertices: &[Vec2],
+    closed: bool,
+    pos: Vec2,
+    rot: f32,
+    shape: Shape,
+) -> ContactManifold {
+    let mut manifold = ContactManifold::new();
+    let rotation = Vec2::from_angle(rot_polyline);
+    let world: SmallVec<[Vec2; 8]> = vertices
+        .iter()
+        .map(|vertex| pos_polyline + rotation.rotate(*vertex))
+        .collect();
+    let n = world.len();
+    let segments = if closed { n } else { n.saturating_sub(1) };
+    let edge = |i: usize| world[(i + 1) % n] - world[i];
+    let bounds = shape.aabb(pos, rot);
+
+    for i in 0..segments {
+        let (start, end) = (world[i], world[(i + 1) % n]);
+        let segment_bounds = Aabb::new(start.min(end), start.max(end));
+        if edge(i).length_squared() <= f32::EPSILON || !segment_bounds.intersects(&bounds) {
+            continue;
+        }
+        let face_normal = edge_normal(&world, i);
+        // Shapes whose center is behind the segment pass through it
+        if face_normal.dot(pos - start) < 0. {
+            continue;
+        }
+        let previous = (closed || i > 0).then(|| (i + segments - 1) % segments);
+        let next = (closed || i + 1 < segments).then(|| (i + 1) % segments);
+
+        let segment = [vertices[i], vertices[(i + 1) % n]];
+        let contacts = collide(
+            pos_polyline,
+            rot_polyline,
+            Shape::Polygon { vertices: &segment },
+            pos,
+            rot,
+            shape,
+        );
+        for contact in contacts {
+            let facing = contact.normal.dot(face_normal);
+            if facing <= 0. {
+                continue;
+            }
+            let t =
+                (pos_polyline + contact.point_a - start).dot(edge(i)) / edge(i).length_squared();
+            let corner = if t <= 1e-4 {
+                previous.map(|previous| (previous, i))
+            } else if t >= 1. - 1e-4 {
+                next.map(|next| (i, next))
+            } else {
+                None
+            };
+            if let Some((before, after)) = corner.filter(|_| facing < 1. - 1e-4) {
+                let convex = edge(before).normalize().perp_dot(edge(after).normalize()) > 1e-3;
+                let normal_before = edge_normal(&world, before);
+                let normal_after = edge_normal(&world, after);
+                let between = normal_before.perp_dot(contact.normal) >= 0.
+                    && contact.normal.perp_dot(normal_after) >= 0.;
+                if !(convex && between) {
+                    continue;
+                }
+            }
+            manifold.push(contact);
+        }
+    }
+    manifold
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    const CAPSULE: Shape = Shape::Capsule {
+        half_height: 0.5,
+        radius: 0.25,
+    };
+
+    fn assert_pushes_up(manifold: &ContactManifold, count: usize, depth: f32) {
+        assert_eq!(manifold.len(), count, "{manifold:?}");
+        for contact in manifold {
+            assert!(contact.normal.abs_diff_eq(Vec2::Y, 1e-5), "{contact:?}");
+            assert!(
+                (contact.penetration_depth - depth).abs() < 1e-5,
+                "{contact:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn stacked_capsules() {
+        let manifold = collide(Vec2::ZERO, 0., CAPSULE, Vec2::new(0., 1.4), 0., CAPSULE);
+        assert_pushes_up(&manifold, 1, 0.1);
+        let contact = manifold[0];
+        assert!(contact.point_a.abs_diff_eq(Vec2::new(0., 0.75), 1e-5));
+        assert!(contact.point_b.abs_diff_eq(Vec2::new(0., -0.75), 1e-5));
+    }
+
+    #[test]
+    fn capsule_on_box() {
+        let ground = Shape::Box {
+            half_extents: Vec2::new(2., 0.5),
+        };
+        let standing = collide(
+            Vec2::new(0., -0.5),
+            0.,
+            ground,
+            Vec2::new(0., 0.7),
+            0.,
+            CAPSULE,
+        );
+        assert_pushes_up(&standing, 1, 0.05);
+        let lying = collide(
+            Vec2::new(0., -0.5),
+            0.,
+            ground,
+            Vec2::new(0., 0.2),
+            FRAC_PI_2,
+            CAPSULE,
+        );
+        assert_pushes_up(&lying, 2, 0.05);
+    }
+
+    #[test]
+    fn capsule_on_polyline_segment() {
+        let ground = Shape::Polyline {
+            vertices: &[Vec2::new(2., 0.), Vec2::new(-2., 0.)],
+            closed: false,
+        };
+        let manifold = collide(
+            Vec2::ZERO,
+            0.,
+            ground,
+            Vec2::new(0., 0.2),
+            FRAC_PI_2,
+            CAPSULE,
+        );
+        assert_pushes_up(&manifold, 2, 0.05);
+    }
+
+    #[test]
+    fn polygon_on_box() {
+        // Clockwise, off center and with a repeated corner
+        let square = ConvexPolygonCollider::new(vec![
+            Vec2::new(1., 1.),
+            Vec2::new(1., 3.),
+            Vec2::new(3., 3.),
+            Vec2::new(3., 3.),
+            Vec2::new(3., 1.),
+        ]);
+        assert_eq!(square.vertices.len(), 4);
+        assert!(square
+            .vertices
+            .iter()
+            .all(|vertex| vertex.abs().abs_diff_eq(Vec2::ONE, 1e-5)));
+        let manifold = collide(
+            Vec2::new(0., -0.5),
+            0.,
+            Shape::Box {
+                half_extents: Vec2::new(2., 0.5),
+            },
+            Vec2::new(0., 0.9),
+            0.,
+            Shape::Polygon {
+                vertices: &square.vertices,
+            },
+        );
+        assert_pushes_up(&manifold, 2, 0.1);
+    }
+
+    #[test]
+    fn empty_polyline() {
+        let empty = Shape::Polyline {
+            vertices: &[],
+            closed: false,
+        };
+        assert_eq!(empty.inertia(1.), 0.);
+        assert!(collide(Vec2::ZERO, 0., empty, Vec2::ZERO, 0., CAPSULE).is_empty());
+    }
+}
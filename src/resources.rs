@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+use crate::solver::ContactConstraint;
+
 #[derive(Debug, Resource)]
 pub struct Gravity(pub Vec2);
 
@@ -9,11 +11,43 @@ impl Default for Gravity {
     }
 }
 
+/// How physics steps are timed.
+#[derive(Debug, Resource, Clone, Copy)]
+pub enum PhysicsTimestep {
+    /// Steps by this many seconds in `FixedUpdate`, catching up when frames take longer.
+    Fixed(f32),
+    /// Steps once per frame in `Update` by the time the frame took, but never by more than
+    /// `max_dt` seconds so a hitch doesn't blow up the simulation.
+    Variable { max_dt: f32 },
+}
+
+impl Default for PhysicsTimestep {
+    fn default() -> Self {
+        Self::Fixed(1. / 60.)
+    }
+}
+
+/// Length of the current physics step.
+#[derive(Debug, Resource, Default)]
+pub struct DeltaTime(pub f32);
+
+/// How many substeps each physics step is split into, more substeps make stacks and joints
+/// stiffer at the cost of running the solver more often. Zero is treated as one.
+#[derive(Debug, Resource)]
+pub struct SubstepCount(pub u32);
+
+impl Default for SubstepCount {
+    fn default() -> Self {
+        Self(8)
+    }
+}
+
+/// Length of a substep, updated before the substeps of each physics step run.
 #[derive(Debug, Resource, Default)]
-pub struct Contacts(pub Vec<(Entity, Entity, Vec2)>);
+pub struct SubDeltaTime(pub f32);
 
 #[derive(Debug, Resource, Default)]
-pub struct StaticContacts(pub Vec<(Entity, Entity, Vec2)>);
+pub struct Contacts(pub Vec<ContactConstraint>);
 
 #[derive(Default, Debug, Resource)]
-pub(crate) struct CollisionPairs(pub Vec<(Entity, Entity)>);
+pub struct CollisionPairs(pub Vec<(Entity, Entity)>);
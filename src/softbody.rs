@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+
+use crate::components::*;
+use crate::narrowphase::polygon_area;
+use crate::resources::{SubDeltaTime, SubstepCount};
+
+/// Keeps the area enclosed by a ring of particles at `pressure` times its rest area, which
+/// makes the ring behave like a balloon.
+#[derive(Component, Debug, Clone)]
+pub struct AreaConstraint {
+    /// Particles around the ring in counter-clockwise order.
+    pub particles: Vec<Entity>,
+    pub rest_area: f32,
+    pub pressure: f32,
+    pub compliance: f32,
+}
+
+/// Pulls a group of particles towards their rest shape, moved and rotated to fit where the
+/// particles are now.
+#[derive(Component, Debug, Clone)]
+pub struct ShapeMatching {
+    pub particles: Vec<Entity>,
+    /// Rest positions relative to their center of mass.
+    pub rest_offsets: Vec<Vec2>,
+    /// Fraction of the way to the rest shape the particles are moved each step, between zero
+    /// and one. It is spread over the substeps so the body is as stiff for any [`SubstepCount`].
+    pub stiffness: f32,
+}
+
+impl ShapeMatching {
+    /// Uses the given positions as the rest shape, assuming equal masses.
+    pub fn new(particles: Vec<Entity>, rest_positions: &[Vec2], stiffness: f32) -> Self {
+        let center = rest_positions.iter().sum::<Vec2>() / rest_positions.len() as f32;
+        Self {
+            particles,
+            rest_offsets: rest_positions.iter().map(|pos| *pos - center).collect(),
+            stiffness,
+        }
+    }
+}
+
+pub(crate) fn solve_area_constraints(
+    constraints: Query<&AreaConstraint>,
+    mut particles: Query<(&mut Pos, &InverseMass)>,
+    sub_dt: Res<SubDeltaTime>,
+    mut positions: Local<Vec<Vec2>>,
+    mut inverse_masses: Local<Vec<f32>>,
+) {
+    for constraint in constraints.iter() {
+        positions.clear();
+        inverse_masses.clear();
+        for (pos, inverse_mass) in particles.iter_many(&constraint.particles) {
+            positions.push(pos.0);
+            inverse_masses.push(inverse_mass.0);
+        }
+        let n = positions.len();
+        if n != constraint.particles.len() || n < 3 {
+            continue;
+        }
+
+        let c = polygon_area(&positions) - constraint.pressure * constraint.rest_area;
+        // Moving a vertex along the normal of the chord between its neighbours grows the
+        // area fastest
+        let gradient = |i: usize| {
+            let chord = positions[(i + 1) % n] - positions[(i + n - 1) % n];
+            Vec2::new(chord.y, -chord.x) / 2.
+        };
+        let w_sum: f32 = (0..n)
+            .map(|i| inverse_masses[i] * gradient(i).length_squared())
+            .sum();
+        let alpha = constraint.compliance / (sub_dt.0 * sub_dt.0);
+        if w_sum + alpha <= f32::EPSILON {
+            continue;
+        }
+        let delta_lagrange = -c / (w_sum + alpha);
+
+        let mut iter = particles.iter_many_mut(&constraint.particles);
+        let mut i = 0;
+        while let Some((mut pos, _)) = iter.fetch_next() {
+            pos.0 += inverse_masses[i] * delta_lagrange * gradient(i);
+            i += 1;
+        }
+    }
+}
+
+pub(crate) fn solve_shape_matching(
+    constraints: Query<&ShapeMatching>,
+    mut particles: Query<(&mut Pos, &Mass, &InverseMass)>,
+    substeps: Res<SubstepCount>,
+) {
+    let substeps = substeps.0.max(1) as f32;
+    for constraint in constraints.iter() {
+        // Moving by `k` each of `n` substeps leaves `(1 - k)^n` of the way, which is what a
+        // single move by `stiffness` leaves
+        let stiffness = 1. - (1. - constraint.stiffness.clamp(0., 1.)).powf(1. / substeps);
+        let (weighted, total_mass) = particles.iter_many(&constraint.particles).fold(
+            (Vec2::ZERO, 0.),
+            |(weighted, total_mass), (pos, mass, _)| {
+                (weighted + pos.0 * mass.0, total_mass + mass.0)
+            },
+        );
+        if total_mass <= 0. {
+            continue;
+        }
+        let center = weighted / total_mass;
+
+        // Best fitting rotation of the rest shape, from the sums of the dot and cross products
+        // between the rest offsets and the current offsets
+        let (cos, sin) = particles
+            .iter_many(&constraint.particles)
+            .zip(constraint.rest_offsets.iter())
+            .fold((0., 0.), |(cos, sin), ((pos, mass, _), rest)| {
+                let offset = pos.0 - center;
+                (
+                    cos + mass.0 * rest.dot(offset),
+                    sin + mass.0 * rest.perp_dot(offset),
+                )
+            });
+        let rotation = Vec2::new(cos, sin).try_normalize().unwrap_or(Vec2::X);
+
+        let mut iter = particles.iter_many_mut(&constraint.particles);
+        let mut rest_offsets = constraint.rest_offsets.iter();
+        while let (Some((mut pos, _, inverse_mass)), Some(rest)) =
+            (iter.fetch_next(), rest_offsets.next())
+        {
+            if inverse_mass.0 == 0. {
+                continue;
+            }
+            let goal = center + rotation.rotate(*rest);
+            pos.0 = pos.0.lerp(goal, stiffness);
+        }
+    }
+}
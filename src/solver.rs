@@ -0,0 +1,324 @@
+use bevy::{prelude::*, utils::smallvec::SmallVec};
+
+use crate::narrowphase::Contact;
+
+/// Contact that was resolved during the position solve, kept for the velocity solve.
+#[derive(Clone, Copy, Debug)]
+pub struct ContactConstraint {
+    pub entity_a: Entity,
+    pub entity_b: Entity,
+    /// Contact points as they were at the end of the position solve.
+    pub contact: Contact,
+    /// Magnitude of the normal correction, dividing it by the timestep squared gives the
+    /// normal force.
+    pub normal_lagrange: f32,
+}
+
+/// Generalized inverse mass of a body pushed along `n` at the offset `r` from its center.
+pub(crate) fn generalized_inverse_mass(
+    inverse_mass: f32,
+    inverse_inertia: f32,
+    r: Vec2,
+    n: Vec2,
+) -> f32 {
+    inverse_mass + inverse_inertia * r.perp_dot(n).powi(2)
+}
+
+/// Pose of a body during the position solve, statics use an inverse mass and inertia of zero.
+pub struct PoseBody<'a> {
+    pub pos: &'a mut Vec2,
+    pub rot: &'a mut f32,
+    pub prev_pos: Vec2,
+    pub prev_rot: f32,
+    /// Orientation when the contacts were computed.
+    pub start_rot: f32,
+    pub inverse_mass: f32,
+    pub inverse_inertia: f32,
+}
+
+impl PoseBody<'_> {
+    /// Carries a contact point along with the rotation since the contacts were computed.
+    fn offset(&self, point: Vec2) -> Vec2 {
+        Vec2::from_angle(*self.rot - self.start_rot).rotate(point)
+    }
+
+    /// Rotates a point from the local space of the body into world orientation, without
+    /// translating it.
+    pub fn to_world(&self, local_point: Vec2) -> Vec2 {
+        Vec2::from_angle(*self.rot).rotate(local_point)
+    }
+
+    /// How far the point at offset `r` moved since the previous substep.
+    pub fn displacement(&self, r: Vec2) -> Vec2 {
+        let prev_r = Vec2::from_angle(self.prev_rot - *self.rot).rotate(r);
+        (*self.pos + r) - (self.prev_pos + prev_r)
+    }
+
+    pub fn generalized_inverse_mass(&self, r: Vec2, n: Vec2) -> f32 {
+        generalized_inverse_mass(self.inverse_mass, self.inverse_inertia, r, n)
+    }
+
+    pub fn apply_positional_impulse(&mut self, p: Vec2, r: Vec2) {
+        *self.pos += p * self.inverse_mass;
+        *self.rot += self.inverse_inertia * r.perp_dot(p);
+    }
+}
+
+/// XPBD update of a Lagrange multiplier for a constraint error `c`, `alpha` is the compliance
+/// scaled by the timestep squared.
+fn lagrange_update(lagrange: f32, c: f32, w_sum: f32, compliance: f32, dt: f32) -> f32 {
+    let alpha = compliance / (dt * dt);
+    (-c - alpha * lagrange) / (w_sum + alpha)
+}
+
+/// Solves the positional constraint `c`, whose gradient is `n` at the offset `r_a` of the first
+/// body and `-n` at the offset `r_b` of the second one.
+///
+/// Adds the correction to `lagrange` and returns it, a compliance of zero makes the constraint
+/// rigid.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_positional_constraint(
+    body_a: &mut PoseBody,
+    body_b: &mut PoseBody,
+    lagrange: &mut f32,
+    c: f32,
+    n: Vec2,
+    r_a: Vec2,
+    r_b: Vec2,
+    compliance: f32,
+    dt: f32,
+) -> f32 {
+    let w_sum = body_a.generalized_inverse_mass(r_a, n) + body_b.generalized_inverse_mass(r_b, n);
+    if w_sum <= f32::EPSILON {
+        return 0.;
+    }
+    let delta_lagrange = lagrange_update(*lagrange, c, w_sum, compliance, dt);
+    *lagrange += delta_lagrange;
+    apply_positional_correction(body_a, body_b, delta_lagrange, n, r_a, r_b);
+    delta_lagrange
+}
+
+/// Pushes the bodies in opposite directions along `n` by the positional correction
+/// `delta_lagrange`, at the offsets `r_a` and `r_b`.
+pub fn apply_positional_correction(
+    body_a: &mut PoseBody,
+    body_b: &mut PoseBody,
+    delta_lagrange: f32,
+    n: Vec2,
+    r_a: Vec2,
+    r_b: Vec2,
+) {
+    let p = n * delta_lagrange;
+    body_a.apply_positional_impulse(p, r_a);
+    body_b.apply_positional_impulse(-p, r_b);
+}
+
+/// Solves the angular constraint `c`, which grows with the rotation of the first body and
+/// shrinks with the rotation of the second one.
+///
+/// Adds the correction to `lagrange` and returns it, a compliance of zero makes the constraint
+/// rigid.
+pub fn apply_angular_constraint(
+    body_a: &mut PoseBody,
+    body_b: &mut PoseBody,
+    lagrange: &mut f32,
+    c: f32,
+    compliance: f32,
+    dt: f32,
+) -> f32 {
+    let w_sum = body_a.inverse_inertia + body_b.inverse_inertia;
+    if w_sum <= f32::EPSILON {
+        return 0.;
+    }
+    let delta_lagrange = lagrange_update(*lagrange, c, w_sum, compliance, dt);
+    *lagrange += delta_lagrange;
+    apply_angular_correction(body_a, body_b, delta_lagrange);
+    delta_lagrange
+}
+
+/// Rotates the bodies in opposite directions by the angular correction `delta_lagrange`,
+/// weighted by their inverse inertia.
+pub fn apply_angular_correction(body_a: &mut PoseBody, body_b: &mut PoseBody, delta_lagrange: f32) {
+    *body_a.rot += body_a.inverse_inertia * delta_lagrange;
+    *body_b.rot -= body_b.inverse_inertia * delta_lagrange;
+}
+
+/// Pushes the bodies apart at the contact points of a manifold and holds them in place with
+/// static friction.
+///
+/// Every point is pushed apart before friction is applied to any of them, otherwise the
+/// rotation from pushing one corner out counts as sliding at the other and a resting body
+/// creeps. Returns the contacts at their final position along with the Lagrange multipliers of
+/// their normal corrections, leaving out the points that were already apart.
+pub(crate) fn solve_manifold_pos(
+    body_a: &mut PoseBody,
+    body_b: &mut PoseBody,
+    manifold: &[Contact],
+    static_friction: f32,
+    dt: f32,
+) -> SmallVec<[(Contact, f32); 2]> {
+    let mut solved: SmallVec<[(Contact, f32); 2]> = manifold
+        .iter()
+        .filter_map(|contact| solve_contact_normal(body_a, body_b, contact, dt))
+        .collect();
+
+    for (contact, normal_lagrange) in solved.iter_mut() {
+        solve_static_friction(
+            body_a,
+            body_b,
+            contact,
+            static_friction * *normal_lagrange,
+            dt,
+        );
+        contact.point_a = body_a.offset(contact.point_a);
+        contact.point_b = body_b.offset(contact.point_b);
+    }
+    solved
+}
+
+/// Pushes the bodies apart at a contact point, returns the contact with the depth it was solved
+/// at and the Lagrange multiplier of the correction, or `None` when the point was already apart.
+fn solve_contact_normal(
+    body_a: &mut PoseBody,
+    body_b: &mut PoseBody,
+    contact: &Contact,
+    dt: f32,
+) -> Option<(Contact, f32)> {
+    let n = contact.normal;
+    let r_a = body_a.offset(contact.point_a);
+    let r_b = body_b.offset(contact.point_b);
+    let penetration_depth = (*body_a.pos + r_a - *body_b.pos - r_b).dot(n);
+    if penetration_depth <= 0. {
+        return None;
+    }
+    let mut normal_lagrange = 0.;
+    apply_positional_constraint(
+        body_a,
+        body_b,
+        &mut normal_lagrange,
+        penetration_depth,
+        n,
+        r_a,
+        r_b,
+        0.,
+        dt,
+    );
+    let contact = Contact {
+        penetration_depth,
+        ..*contact
+    };
+    Some((contact, -normal_lagrange))
+}
+
+/// Undoes the sliding of the contact points this step, as long as that takes less than
+/// `max_lagrange`.
+fn solve_static_friction(
+    body_a: &mut PoseBody,
+    body_b: &mut PoseBody,
+    contact: &Contact,
+    max_lagrange: f32,
+    dt: f32,
+) {
+    let n = contact.normal;
+    let r_a = body_a.offset(contact.point_a);
+    let r_b = body_b.offset(contact.point_b);
+    let delta_p = body_a.displacement(r_a) - body_b.displacement(r_b);
+    let delta_p_tangent = delta_p - n * delta_p.dot(n);
+    let sliding = delta_p_tangent.length();
+    if sliding <= f32::EPSILON {
+        return;
+    }
+    let t = delta_p_tangent / sliding;
+    let w_a = body_a.generalized_inverse_mass(r_a, t);
+    let w_b = body_b.generalized_inverse_mass(r_b, t);
+    let tangent_lagrange = sliding / (w_a + w_b);
+    if tangent_lagrange < max_lagrange {
+        let mut tangent_lagrange = 0.;
+        apply_positional_constraint(
+            body_a,
+            body_b,
+            &mut tangent_lagrange,
+            sliding,
+            t,
+            r_a,
+            r_b,
+            0.,
+            dt,
+        );
+    }
+}
+
+/// Velocities of a body during the velocity solve, statics use an inverse mass and inertia of
+/// zero.
+pub(crate) struct VelocityBody<'a> {
+    pub vel: &'a mut Vec2,
+    pub ang_vel: &'a mut f32,
+    pub pre_solve_vel: Vec2,
+    pub pre_solve_ang_vel: f32,
+    pub inverse_mass: f32,
+    pub inverse_inertia: f32,
+}
+
+impl VelocityBody<'_> {
+    fn point_velocity(&self, r: Vec2) -> Vec2 {
+        *self.vel + *self.ang_vel * r.perp()
+    }
+
+    fn pre_solve_point_velocity(&self, r: Vec2) -> Vec2 {
+        self.pre_solve_vel + self.pre_solve_ang_vel * r.perp()
+    }
+
+    fn generalized_inverse_mass(&self, r: Vec2, n: Vec2) -> f32 {
+        generalized_inverse_mass(self.inverse_mass, self.inverse_inertia, r, n)
+    }
+
+    fn apply_impulse(&mut self, p: Vec2, r: Vec2) {
+        *self.vel += p * self.inverse_mass;
+        *self.ang_vel += self.inverse_inertia * r.perp_dot(p);
+    }
+}
+
+/// Applies restitution along the contact normal and dynamic friction along the tangent.
+pub(crate) fn solve_contact_vel(
+    body_a: &mut VelocityBody,
+    body_b: &mut VelocityBody,
+    constraint: &ContactConstraint,
+    restitution: f32,
+    dynamic_friction: f32,
+    dt: f32,
+) {
+    let n = constraint.contact.normal;
+    let r_a = constraint.contact.point_a;
+    let r_b = constraint.contact.point_b;
+    let pre_solve_relative_vel =
+        body_a.pre_solve_point_velocity(r_a) - body_b.pre_solve_point_velocity(r_b);
+    let pre_solve_normal_vel = Vec2::dot(pre_solve_relative_vel, n);
+    let relative_vel = body_a.point_velocity(r_a) - body_b.point_velocity(r_b);
+    let normal_vel = Vec2::dot(relative_vel, n);
+    let tangent_vel = relative_vel - n * normal_vel;
+
+    // Two bodies that can't be moved, like a kinematic body touching a static one
+    let w_sum = body_a.generalized_inverse_mass(r_a, n) + body_b.generalized_inverse_mass(r_b, n);
+    if w_sum <= f32::EPSILON {
+        return;
+    }
+    let restitution_velocity = (-restitution * pre_solve_normal_vel).min(0.);
+    let vel_impulse = n * ((-normal_vel + restitution_velocity) / w_sum);
+    body_a.apply_impulse(vel_impulse, r_a);
+    body_b.apply_impulse(-vel_impulse, r_b);
+
+    let tangent_speed = tangent_vel.length();
+    if tangent_speed <= f32::EPSILON {
+        return;
+    }
+    let t = tangent_vel / tangent_speed;
+    let w_sum = body_a.generalized_inverse_mass(r_a, t) + body_b.generalized_inverse_mass(r_b, t);
+    if w_sum <= f32::EPSILON {
+        return;
+    }
+    let normal_force = constraint.normal_lagrange / dt.powi(2);
+    let delta_v = (dynamic_friction * normal_force * dt).min(tangent_speed);
+    let friction_impulse = -t * (delta_v / w_sum);
+    body_a.apply_impulse(friction_impulse, r_a);
+    body_b.apply_impulse(-friction_impulse, r_b);
+}